@@ -0,0 +1,33 @@
+#![no_main]
+
+//! Feeds arbitrary diffs and files to the diff engines; run with `cargo fuzz run apply_diff`.
+//!
+//! The input is split at its first NUL byte into the diff and the original content. The
+//! diff must be UTF-8, the original content may be any bytes.
+
+use libfuzzer_sys::fuzz_target;
+use replace_in_file::{
+    construct_new_file_content_v1, construct_new_file_content_v1_bytes,
+    construct_new_file_content_v2, construct_new_file_content_v2_bytes,
+    construct_new_file_content_xml,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let (diff, original) = match data.iter().position(|&byte| byte == 0) {
+        Some(nul) => (&data[..nul], &data[nul + 1..]),
+        None => (data, &[][..]),
+    };
+    let Ok(diff) = std::str::from_utf8(diff) else {
+        return;
+    };
+
+    for is_final in [false, true] {
+        let _ = construct_new_file_content_v1_bytes(diff, original, is_final);
+        let _ = construct_new_file_content_v2_bytes(diff, original, is_final);
+        if let Ok(original) = std::str::from_utf8(original) {
+            let _ = construct_new_file_content_v1(diff, original, is_final);
+            let _ = construct_new_file_content_v2(diff, original, is_final);
+            let _ = construct_new_file_content_xml(diff, original, is_final);
+        }
+    }
+});
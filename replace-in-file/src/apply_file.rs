@@ -0,0 +1,54 @@
+use std::fs;
+use std::path::Path;
+
+use crate::atomic_write::write_atomically;
+use crate::{
+    DiffError, DiffOptions, TextEncoding, construct_new_file_content_v2_with_options, decode_text,
+    encode_text,
+};
+
+/// The result of a successful [`apply_to_path`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApplyOutcome {
+    /// The new content of the file, decoded
+    pub content: String,
+    /// The encoding the file was read and written in
+    pub encoding: TextEncoding,
+}
+
+/// Applies a SEARCH/REPLACE diff to a file with the v2 engine.
+///
+/// The file is decoded with [`decode_text`] for matching and the result is written back
+/// in the same encoding, keeping a byte order mark if it had one. Nothing is written if
+/// the diff fails or the new content can't be represented in that encoding.
+///
+/// The file is replaced atomically through a temporary file in its directory, keeping its
+/// permissions and owner; for a symlink, the target is edited and the link kept.
+/// With [`DiffOptions::expected_original`] set, a file changed since it was read is
+/// refused with [`DiffError::StaleOriginal`].
+pub fn apply_to_path(
+    path: &Path,
+    diff_content: &str,
+    options: &DiffOptions,
+) -> Result<ApplyOutcome, DiffError> {
+    let metadata = match fs::metadata(path) {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Err(DiffError::PathNotFound(path.to_path_buf()));
+        }
+        result => result?,
+    };
+    if let Some(precondition) = &options.expected_original {
+        precondition.check_file(&metadata)?;
+    }
+    // Refuse oversized files before reading them
+    let size = usize::try_from(metadata.len()).unwrap_or(usize::MAX);
+    options.limits.check_file_size(size)?;
+    let raw = fs::read(path)?;
+    let (original, encoding) = decode_text(&raw)?;
+
+    let content =
+        construct_new_file_content_v2_with_options(diff_content, &original, true, options)?;
+    write_atomically(path, &encode_text(&content, encoding)?, options.sync_writes)?;
+
+    Ok(ApplyOutcome { content, encoding })
+}
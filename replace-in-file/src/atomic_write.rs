@@ -0,0 +1,123 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Symlinks followed before giving up, like the kernel's limit
+const MAX_SYMLINK_HOPS: usize = 40;
+
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Replaces the content of a file so that readers see either the old or the new content,
+/// never a partial write.
+///
+/// The content goes to a temporary file in the same directory, which is then renamed over
+/// the file. The file keeps its permissions and, where allowed, its owner; a symlink
+/// keeps pointing to its target, which is what gets replaced. With `sync` the data and
+/// the rename are flushed to disk before returning.
+pub(crate) fn write_atomically(path: &Path, content: &[u8], sync: bool) -> io::Result<()> {
+    let path = follow_symlinks(path)?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let original = match fs::metadata(&path) {
+        Ok(metadata) => Some(metadata),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err),
+    };
+
+    let (temp_path, mut temp) = create_temp_file(dir, &path)?;
+    let result = (|| {
+        temp.write_all(content)?;
+        if let Some(original) = &original {
+            // Changing the owner can clear setuid bits, so permissions come last
+            #[cfg(unix)]
+            copy_owner(&temp, original);
+            temp.set_permissions(original.permissions())?;
+        }
+        if sync {
+            temp.sync_all()?;
+        }
+        drop(temp);
+        fs::rename(&temp_path, &path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result?;
+
+    #[cfg(unix)]
+    if sync {
+        // Makes the rename itself durable
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// The path a chain of symlinks ends at, whether or not that file exists
+fn follow_symlinks(path: &Path) -> io::Result<PathBuf> {
+    let mut path = path.to_path_buf();
+    let mut hops = 0;
+    while let Some(target) = symlink_target(&path, &mut hops)? {
+        path = target;
+    }
+    Ok(path)
+}
+
+/// Where the symlink at `path` points, relative to its directory, or `None` if it isn't
+/// one. `hops` counts the links followed so far, and cycles are cut off like the kernel
+/// does.
+pub(crate) fn symlink_target(path: &Path, hops: &mut usize) -> io::Result<Option<PathBuf>> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => {}
+        _ => return Ok(None),
+    }
+    *hops += 1;
+    if *hops > MAX_SYMLINK_HOPS {
+        return Err(io::Error::other(format!(
+            "too many levels of symbolic links at {}",
+            path.display()
+        )));
+    }
+    let target = fs::read_link(path)?;
+    Ok(Some(match path.parent() {
+        Some(parent) => parent.join(target),
+        None => target,
+    }))
+}
+
+fn create_temp_file(dir: &Path, path: &Path) -> io::Result<(PathBuf, File)> {
+    let name = path
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    loop {
+        let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let temp_path = dir.join(format!(".{name}.{}.{counter}.tmp", std::process::id()));
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+        {
+            Ok(file) => return Ok((temp_path, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Gives the file the owner and group of the original, as far as the process may
+#[cfg(unix)]
+fn copy_owner(file: &File, original: &fs::Metadata) {
+    use std::os::unix::fs::{MetadataExt, fchown};
+
+    let Ok(current) = file.metadata() else {
+        return;
+    };
+    if (current.uid(), current.gid()) != (original.uid(), original.gid()) {
+        // Only privileged processes can give files away; the group alone may still work
+        if fchown(file, Some(original.uid()), Some(original.gid())).is_err() {
+            let _ = fchown(file, None, Some(original.gid()));
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{
+    DiffError, DiffOptions, construct_new_file_content_v1,
+    construct_new_file_content_v2_with_options,
+};
+
+/// The engines work on text, so raw content is decoded losslessly first: valid UTF-8 is
+/// kept as is and every other byte becomes a character of its own, `ESCAPE_BASE + byte`,
+/// from the end of the last private use plane (U+10FF80 to U+10FFFF). Those characters
+/// never match diff text, but they are carried through matching and splicing unchanged and
+/// turn back into the very same bytes afterwards. Characters of that range that actually
+/// occur in the file or the diff are escaped byte by byte, so they survive the round trip.
+const ESCAPE_BASE: u32 = 0x10FF00;
+
+fn is_escape(c: char) -> bool {
+    (ESCAPE_BASE + 0x80..=ESCAPE_BASE + 0xFF).contains(&(c as u32))
+}
+
+fn escape_byte(byte: u8) -> char {
+    char::from_u32(ESCAPE_BASE + u32::from(byte)).expect("escapes are valid code points")
+}
+
+fn push_escaped_str(out: &mut String, text: &str) {
+    for c in text.chars() {
+        if is_escape(c) {
+            let mut buf = [0; 4];
+            out.extend(c.encode_utf8(&mut buf).bytes().map(escape_byte));
+        } else {
+            out.push(c);
+        }
+    }
+}
+
+/// Decodes content into text that [`encode`] turns back into the same bytes
+fn decode(content: &[u8]) -> String {
+    let mut text = String::with_capacity(content.len());
+    for chunk in content.utf8_chunks() {
+        push_escaped_str(&mut text, chunk.valid());
+        text.extend(chunk.invalid().iter().copied().map(escape_byte));
+    }
+    text
+}
+
+/// Escapes diff text the same way as decoded content, so both agree on every character
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    push_escaped_str(&mut escaped, text);
+    escaped
+}
+
+fn encode(text: &str) -> Vec<u8> {
+    let mut content = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        if is_escape(c) {
+            content.push((c as u32 - ESCAPE_BASE) as u8);
+        } else {
+            content.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+        }
+    }
+    content
+}
+
+/// Same as [`construct_new_file_content_v1`], for content in any encoding. Only the diff
+/// must be UTF-8; bytes of the file outside the replaced ranges are kept exactly.
+pub fn construct_new_file_content_v1_bytes(
+    diff_content: &str,
+    original_content: &[u8],
+    is_final: bool,
+) -> Result<Vec<u8>, DiffError> {
+    let original = decode(original_content);
+    construct_new_file_content_v1(&escape(diff_content), &original, is_final)
+        .map(|content| encode(&content))
+}
+
+/// Same as [`construct_new_file_content_v2`](crate::construct_new_file_content_v2), for
+/// content in any encoding. Only the diff must be UTF-8; bytes of the file outside the
+/// replaced ranges are kept exactly.
+pub fn construct_new_file_content_v2_bytes(
+    diff_content: &str,
+    original_content: &[u8],
+    is_final: bool,
+) -> Result<Vec<u8>, DiffError> {
+    construct_new_file_content_v2_bytes_with_options(
+        diff_content,
+        original_content,
+        is_final,
+        &DiffOptions::default(),
+    )
+}
+
+/// Same as [`construct_new_file_content_v2_bytes`], with custom [`DiffOptions`]
+pub fn construct_new_file_content_v2_bytes_with_options(
+    diff_content: &str,
+    original_content: &[u8],
+    is_final: bool,
+    options: &DiffOptions,
+) -> Result<Vec<u8>, DiffError> {
+    let original = decode(original_content);
+    construct_new_file_content_v2_with_options(&escape(diff_content), &original, is_final, options)
+        .map(|content| encode(&content))
+}
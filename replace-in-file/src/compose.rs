@@ -0,0 +1,231 @@
+use std::ops::Range;
+
+use crate::{
+    DiffError, DiffOptions, EditBlock, LineIndex, Splice, format_search_replace_blocks,
+    resolve_block_splices,
+};
+
+/// A block of the second diff that edits text the first diff introduced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockDependency {
+    /// Index of the block in the first diff
+    pub first: usize,
+    /// Index of the block in the second diff
+    pub second: usize,
+}
+
+/// The result of [`compose_diffs`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Composition {
+    /// A single diff turning the original content into the result of both diffs
+    pub diff: String,
+    /// Blocks of the second diff depending on the first, in the order of the second diff
+    pub dependencies: Vec<BlockDependency>,
+}
+
+/// Composes two diffs applied one after the other into a single diff against the original.
+///
+/// `first` is resolved against `original` and `second` against its result. The composed
+/// diff has one block per region of the original the two diffs change, widened to whole
+/// lines and with enough leading context to match there first. A block of `second` that
+/// replaces text `first` inserted, or inserts text next to it, is reported as a
+/// dependency. Like [`search_replace_equivalent`](crate::search_replace_equivalent),
+/// the composed diff doesn't represent a missing final newline.
+pub fn compose_diffs(
+    first: &str,
+    second: &str,
+    original: &str,
+    options: &DiffOptions,
+) -> Result<Composition, DiffError> {
+    let first_edits = sorted_edits(resolve_block_splices(first, original, options)?);
+    let intermediate = apply_edits(original, &first_edits);
+    let second_edits = sorted_edits(resolve_block_splices(second, &intermediate, options)?);
+    let updated = apply_edits(&intermediate, &second_edits);
+
+    let mut dependencies: Vec<BlockDependency> = Vec::new();
+    let mut regions: Vec<Range<usize>> = first_edits.iter().map(|(_, e)| e.range.clone()).collect();
+    for (second_block, edit) in &second_edits {
+        for (first_block, span) in inserted_spans(&first_edits) {
+            let depends = if edit.range.is_empty() {
+                span.start <= edit.range.start && edit.range.start <= span.end
+            } else {
+                edit.range.start < span.end && span.start < edit.range.end
+            };
+            let dependency = BlockDependency {
+                first: first_block,
+                second: *second_block,
+            };
+            if depends && !dependencies.contains(&dependency) {
+                dependencies.push(dependency);
+            }
+        }
+        regions.push(
+            map_back(&first_edits, edit.range.start, false)
+                ..map_back(&first_edits, edit.range.end, true),
+        );
+    }
+    dependencies.sort_by_key(|d| (d.second, d.first));
+
+    let lines = LineIndex::new(original, options.max_fuzzy_line_length);
+    let mut blocks = Vec::new();
+    let mut processed = 0;
+    // Regions the edits leave as they were, which don't make blocks
+    let mut unchanged: Vec<Range<usize>> = Vec::new();
+    let updated_end = |end| map_forward(&second_edits, map_forward(&first_edits, end, true), true);
+    let mut regions = merge_regions(&lines, regions).into_iter().peekable();
+    while let Some(mut region) = regions.next() {
+        // An edit joining lines leaves the end of the region inside an updated line, which
+        // the region then has to take in whole
+        while region.end < original.len()
+            && updated_end(region.end) < updated.len()
+            && !updated[..updated_end(region.end)].ends_with('\n')
+        {
+            region.end = lines.start_of(lines.line_containing(region.end) + 1);
+            while let Some(next) = regions.next_if(|next| next.start <= region.end) {
+                region.end = region.end.max(next.end);
+            }
+        }
+
+        let mut start = region.start;
+        // The engine takes the first occurrence after the previous block
+        while start > processed
+            && original[processed..].find(&original[start..region.end]) != Some(start - processed)
+        {
+            start = lines.start_of(lines.line_containing(start - 1));
+            if let Some(skipped) = unchanged.iter().find(|r| r.start < start && start < r.end) {
+                start = skipped.start;
+            }
+        }
+        let updated_start = map_forward(
+            &second_edits,
+            map_forward(&first_edits, start, false),
+            false,
+        );
+        let search = &original[start..region.end];
+        let replace = &updated[updated_start..updated_end(region.end)];
+        if search == replace {
+            unchanged.push(region);
+            continue;
+        }
+        blocks.push(EditBlock {
+            search: search.to_string(),
+            replace: replace.to_string(),
+            ..Default::default()
+        });
+        processed = region.end;
+    }
+
+    Ok(Composition {
+        diff: format_search_replace_blocks(&blocks, &options.markers),
+        dependencies,
+    })
+}
+
+/// The edits of every block, tagged with the block's index, in file order
+fn sorted_edits(blocks: Vec<Vec<Splice>>) -> Vec<(usize, Splice)> {
+    let mut edits: Vec<(usize, Splice)> = blocks
+        .into_iter()
+        .enumerate()
+        .flat_map(|(block, splices)| splices.into_iter().map(move |splice| (block, splice)))
+        .collect();
+    edits.sort_by_key(|(_, edit)| edit.key());
+    edits
+}
+
+fn apply_edits(content: &str, edits: &[(usize, Splice)]) -> String {
+    let mut result = String::with_capacity(content.len());
+    let mut position = 0;
+    for (_, edit) in edits {
+        result.push_str(&content[position..edit.range.start]);
+        result.push_str(&edit.replacement);
+        position = edit.range.end;
+    }
+    result.push_str(&content[position..]);
+    result
+}
+
+/// Where the replacement text of each edit ended up in the edited content
+fn inserted_spans(edits: &[(usize, Splice)]) -> impl Iterator<Item = (usize, Range<usize>)> + '_ {
+    let mut shift = 0isize;
+    edits.iter().filter_map(move |(block, edit)| {
+        let start = (edit.range.start as isize + shift) as usize;
+        shift += edit.replacement.len() as isize - edit.range.len() as isize;
+        (!edit.replacement.is_empty()).then(|| (*block, start..start + edit.replacement.len()))
+    })
+}
+
+/// Maps a position of the content before the edits, outside of the ranges they replace,
+/// to the edited content, after the text inserted there if `after_insertions`
+fn map_forward(edits: &[(usize, Splice)], position: usize, after_insertions: bool) -> usize {
+    let mut mapped = position;
+    for (_, edit) in edits {
+        let is_before = if edit.range.is_empty() {
+            edit.range.start < position || (after_insertions && edit.range.start == position)
+        } else {
+            edit.range.end <= position
+        };
+        if !is_before {
+            break;
+        }
+        mapped = mapped + edit.replacement.len() - edit.range.len();
+    }
+    mapped
+}
+
+/// Maps a position of the edited content back to the content before the edits. A
+/// position within replacement text maps to the start of the range it replaced, or to
+/// its end for the `end` of a range.
+fn map_back(edits: &[(usize, Splice)], position: usize, end: bool) -> usize {
+    let mut shift = 0isize;
+    for (_, edit) in edits {
+        let start = (edit.range.start as isize + shift) as usize;
+        let inserted_end = start + edit.replacement.len();
+        let (is_before, is_within) = if end {
+            (position <= start, position <= inserted_end)
+        } else {
+            (position < start, position < inserted_end)
+        };
+        if is_before {
+            break;
+        }
+        if is_within {
+            return if end {
+                edit.range.end
+            } else {
+                edit.range.start
+            };
+        }
+        shift += edit.replacement.len() as isize - edit.range.len() as isize;
+    }
+    (position as isize - shift) as usize
+}
+
+/// Widens the regions to whole lines, an insertion taking the line before it (or after
+/// it at the start), and merges those that touch
+fn merge_regions(lines: &LineIndex, regions: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    let mut regions: Vec<Range<usize>> = regions
+        .into_iter()
+        .map(|region| {
+            if region.is_empty() {
+                match region.start {
+                    0 => 0..lines.start_of(1),
+                    point => lines.start_of(lines.line_containing(point - 1))..point,
+                }
+            } else {
+                let first = lines.line_containing(region.start);
+                let last = lines.line_containing(region.end - 1);
+                lines.start_of(first)..lines.start_of(last + 1)
+            }
+        })
+        .collect();
+    regions.sort_by_key(|r| (r.start, r.end));
+
+    let mut merged: Vec<Range<usize>> = Vec::new();
+    for region in regions {
+        match merged.last_mut() {
+            Some(last) if region.start <= last.end => last.end = last.end.max(region.end),
+            _ => merged.push(region),
+        }
+    }
+    merged
+}
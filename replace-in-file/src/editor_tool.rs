@@ -0,0 +1,294 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::atomic_write::write_atomically;
+use crate::{
+    DiffError, DiffOptions, Precondition, StrReplaceRequest, Workspace,
+    construct_new_file_content_v2_with_options, str_replace,
+};
+
+/// Maximum directory depth listed by `view`
+const VIEW_DIRECTORY_DEPTH: usize = 2;
+
+/// A text-editor tool call, tagged by its `command` field like
+/// `{"command": "view", "path": "src/lib.rs", "view_range": [1, 20]}`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum EditorCommand {
+    /// Shows a file with line numbers, or lists a directory.
+    /// `view_range` is 1-based and inclusive; an end of `-1` means the end of the file.
+    View {
+        path: String,
+        #[serde(default)]
+        view_range: Option<(usize, i64)>,
+    },
+    /// Creates a file, refusing to replace an existing one unless `overwrite` is set
+    Create {
+        path: String,
+        file_text: String,
+        #[serde(default)]
+        overwrite: bool,
+    },
+    StrReplace(StrReplaceRequest),
+    /// Applies a SEARCH/REPLACE diff, if given only to the content hashing to
+    /// `expected_sha256` (see [`content_sha256`](crate::content_sha256))
+    ReplaceInFile {
+        path: String,
+        diff: String,
+        #[serde(default)]
+        expected_sha256: Option<String>,
+    },
+    /// Inserts `new_str` after line `insert_line` (0 inserts at the top of the file)
+    Insert {
+        path: String,
+        insert_line: usize,
+        new_str: String,
+    },
+    /// Reverts the last edit made to the file through this tool
+    UndoEdit {
+        path: String,
+    },
+}
+
+/// Executes [`EditorCommand`]s and keeps a per-file history for `undo_edit`
+#[derive(Debug, Default)]
+pub struct EditorTool {
+    /// Content before each edit, or `None` if the edit created the file
+    history: HashMap<PathBuf, Vec<Option<String>>>,
+    /// Confines every path, if set
+    workspace: Option<Workspace>,
+}
+
+impl EditorTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolves every path of the commands with [`Workspace::resolve`]
+    pub fn with_workspace(workspace: Workspace) -> Self {
+        Self {
+            workspace: Some(workspace),
+            ..Self::default()
+        }
+    }
+
+    fn resolve(&self, path: &str) -> Result<PathBuf, DiffError> {
+        match &self.workspace {
+            Some(workspace) => workspace.resolve(path),
+            None => Ok(PathBuf::from(path)),
+        }
+    }
+
+    /// Runs a command, returning the output to show to the agent
+    pub fn execute(&mut self, command: &EditorCommand) -> Result<String, DiffError> {
+        match command {
+            EditorCommand::View { path, view_range } => {
+                self.view(&self.resolve(path)?, *view_range)
+            }
+            EditorCommand::Create {
+                path,
+                file_text,
+                overwrite,
+            } => self.create(&self.resolve(path)?, file_text, *overwrite),
+            EditorCommand::StrReplace(request) => {
+                let path = &self.resolve(&request.path)?;
+                let content = read_existing_file(path)?;
+                let outcome = str_replace(&content, request)?;
+                self.write_with_history(path, Some(content), &outcome.content)?;
+                Ok(format!("The file {} has been edited.", path.display()))
+            }
+            EditorCommand::ReplaceInFile {
+                path,
+                diff,
+                expected_sha256,
+            } => {
+                let path = &self.resolve(path)?;
+                let content = read_existing_file(path)?;
+                let options = DiffOptions {
+                    expected_original: expected_sha256.clone().map(Precondition::Sha256),
+                    ..Default::default()
+                };
+                let new_content =
+                    construct_new_file_content_v2_with_options(diff, &content, true, &options)?;
+                self.write_with_history(path, Some(content), &new_content)?;
+                Ok(format!("The file {} has been edited.", path.display()))
+            }
+            EditorCommand::Insert {
+                path,
+                insert_line,
+                new_str,
+            } => self.insert(&self.resolve(path)?, *insert_line, new_str),
+            EditorCommand::UndoEdit { path } => self.undo_edit(&self.resolve(path)?),
+        }
+    }
+
+    fn view(&self, path: &Path, view_range: Option<(usize, i64)>) -> Result<String, DiffError> {
+        if path.is_dir() {
+            if view_range.is_some() {
+                return Err(DiffError::InvalidViewRange(
+                    "`view_range` is not allowed when `path` points to a directory".to_string(),
+                ));
+            }
+            let mut entries = Vec::new();
+            list_directory(path, VIEW_DIRECTORY_DEPTH, &mut entries)?;
+            entries.sort();
+            return Ok(entries
+                .iter()
+                .map(|entry| entry.display().to_string())
+                .collect::<Vec<_>>()
+                .join("\n"));
+        }
+
+        let content = read_existing_file(path)?;
+        let lines: Vec<&str> = content.lines().collect();
+        let (start, end) = match view_range {
+            None => (1, lines.len()),
+            Some((start, end)) => {
+                if start == 0 || start > lines.len().max(1) {
+                    return Err(DiffError::InvalidViewRange(format!(
+                        "start line {start} should be within [1, {}]",
+                        lines.len()
+                    )));
+                }
+                let end = match end {
+                    -1 => lines.len(),
+                    end if end < start as i64 || end as usize > lines.len() => {
+                        return Err(DiffError::InvalidViewRange(format!(
+                            "end line {end} should be -1 or within [{start}, {}]",
+                            lines.len()
+                        )));
+                    }
+                    end => end as usize,
+                };
+                (start, end)
+            }
+        };
+
+        Ok(lines
+            .iter()
+            .enumerate()
+            .take(end)
+            .skip(start - 1)
+            .map(|(i, line)| format!("{:>6}\t{line}", i + 1))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    fn create(
+        &mut self,
+        path: &Path,
+        file_text: &str,
+        overwrite: bool,
+    ) -> Result<String, DiffError> {
+        let previous = match fs::read_to_string(path) {
+            Ok(_) if !overwrite => return Err(DiffError::FileAlreadyExists(path.to_path_buf())),
+            Ok(content) => Some(content),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        self.write_with_history(path, previous, file_text)?;
+        Ok(format!("File created successfully at: {}", path.display()))
+    }
+
+    fn insert(
+        &mut self,
+        path: &Path,
+        insert_line: usize,
+        new_str: &str,
+    ) -> Result<String, DiffError> {
+        let content = read_existing_file(path)?;
+        let line_count = content.lines().count();
+        if insert_line > line_count {
+            return Err(DiffError::InvalidInsertLine {
+                line: insert_line,
+                line_count,
+            });
+        }
+
+        // Byte offset just past line `insert_line`
+        let offset = match insert_line {
+            0 => 0,
+            line => content
+                .match_indices('\n')
+                .nth(line - 1)
+                .map_or(content.len(), |(i, _)| i + 1),
+        };
+
+        let mut new_content = String::with_capacity(content.len() + new_str.len() + 1);
+        new_content.push_str(&content[..offset]);
+        if offset > 0 && !new_content.ends_with('\n') {
+            new_content.push('\n');
+        }
+        new_content.push_str(new_str);
+        if !new_str.ends_with('\n') && offset < content.len() {
+            new_content.push('\n');
+        }
+        new_content.push_str(&content[offset..]);
+
+        self.write_with_history(path, Some(content), &new_content)?;
+        Ok(format!("The file {} has been edited.", path.display()))
+    }
+
+    fn undo_edit(&mut self, path: &Path) -> Result<String, DiffError> {
+        let previous = self
+            .history
+            .get_mut(path)
+            .and_then(Vec::pop)
+            .ok_or_else(|| DiffError::NoEditHistory(path.to_path_buf()))?;
+        match previous {
+            Some(content) => write_atomically(path, content.as_bytes(), false)?,
+            None => fs::remove_file(path)?,
+        }
+        Ok(format!(
+            "Last edit to {} undone successfully.",
+            path.display()
+        ))
+    }
+
+    fn write_with_history(
+        &mut self,
+        path: &Path,
+        previous: Option<String>,
+        new_content: &str,
+    ) -> Result<(), DiffError> {
+        write_atomically(path, new_content.as_bytes(), false)?;
+        self.history
+            .entry(path.to_path_buf())
+            .or_default()
+            .push(previous);
+        Ok(())
+    }
+}
+
+fn read_existing_file(path: &Path) -> Result<String, DiffError> {
+    match fs::read_to_string(path) {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            Err(DiffError::PathNotFound(path.to_path_buf()))
+        }
+        result => Ok(result?),
+    }
+}
+
+/// Collects non-hidden entries below `dir`, descending at most `depth` levels
+fn list_directory(dir: &Path, depth: usize, entries: &mut Vec<PathBuf>) -> Result<(), DiffError> {
+    if depth == 0 {
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            list_directory(&path, depth - 1, entries)?;
+        }
+        entries.push(path);
+    }
+    Ok(())
+}
@@ -0,0 +1,241 @@
+use std::fmt;
+
+use crate::DiffError;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16_LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16_BE_BOM: &[u8] = b"\xFE\xFF";
+
+/// Bytes looked at to recognize UTF-16 without a byte order mark
+const UTF16_SAMPLE_BYTES: usize = 4096;
+
+/// Characters of the bytes 0x80 to 0x9F in Windows-1252; the rest match Latin-1.
+/// Bytes left undefined are `None`.
+const WINDOWS_1252_HIGH: [Option<char>; 32] = [
+    Some('\u{20AC}'),
+    None,
+    Some('\u{201A}'),
+    Some('\u{0192}'),
+    Some('\u{201E}'),
+    Some('\u{2026}'),
+    Some('\u{2020}'),
+    Some('\u{2021}'),
+    Some('\u{02C6}'),
+    Some('\u{2030}'),
+    Some('\u{0160}'),
+    Some('\u{2039}'),
+    Some('\u{0152}'),
+    None,
+    Some('\u{017D}'),
+    None,
+    None,
+    Some('\u{2018}'),
+    Some('\u{2019}'),
+    Some('\u{201C}'),
+    Some('\u{201D}'),
+    Some('\u{2022}'),
+    Some('\u{2013}'),
+    Some('\u{2014}'),
+    Some('\u{02DC}'),
+    Some('\u{2122}'),
+    Some('\u{0161}'),
+    Some('\u{203A}'),
+    Some('\u{0153}'),
+    None,
+    Some('\u{017E}'),
+    Some('\u{0178}'),
+];
+
+/// How the content of a file is encoded, as recognized by [`decode_text`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    Utf8 {
+        bom: bool,
+    },
+    Utf16Le {
+        bom: bool,
+    },
+    Utf16Be {
+        bom: bool,
+    },
+    /// Single-byte Western European text that isn't valid UTF-8
+    Windows1252,
+    /// Single-byte text using bytes Windows-1252 leaves undefined
+    Latin1,
+}
+
+impl Default for TextEncoding {
+    fn default() -> Self {
+        Self::Utf8 { bom: false }
+    }
+}
+
+impl fmt::Display for TextEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, bom) = match *self {
+            Self::Utf8 { bom } => ("UTF-8", bom),
+            Self::Utf16Le { bom } => ("UTF-16LE", bom),
+            Self::Utf16Be { bom } => ("UTF-16BE", bom),
+            Self::Windows1252 => ("Windows-1252", false),
+            Self::Latin1 => ("ISO-8859-1", false),
+        };
+        f.write_str(name)?;
+        if bom {
+            f.write_str(" with BOM")?;
+        }
+        Ok(())
+    }
+}
+
+/// Recognizes the encoding of file content.
+///
+/// A byte order mark decides; otherwise mostly-ASCII UTF-16 is recognized by its zero
+/// bytes, then valid UTF-8 is taken as such and anything else as a single-byte encoding.
+pub fn detect_encoding(content: &[u8]) -> TextEncoding {
+    if content.starts_with(UTF8_BOM) {
+        TextEncoding::Utf8 { bom: true }
+    } else if content.starts_with(UTF16_LE_BOM) {
+        TextEncoding::Utf16Le { bom: true }
+    } else if content.starts_with(UTF16_BE_BOM) {
+        TextEncoding::Utf16Be { bom: true }
+    } else if let Some(encoding) = detect_utf16_without_bom(content) {
+        encoding
+    } else if std::str::from_utf8(content).is_ok() {
+        TextEncoding::Utf8 { bom: false }
+    } else if content
+        .iter()
+        .any(|&byte| (0x80..0xA0).contains(&byte) && windows_1252_char(byte).is_none())
+    {
+        TextEncoding::Latin1
+    } else {
+        TextEncoding::Windows1252
+    }
+}
+
+/// UTF-16 text that is mostly ASCII has a zero in every other byte
+fn detect_utf16_without_bom(content: &[u8]) -> Option<TextEncoding> {
+    if content.len() < 2 || !content.len().is_multiple_of(2) {
+        return None;
+    }
+    let sample = &content[..content.len().min(UTF16_SAMPLE_BYTES)];
+    let pairs = sample.len() / 2;
+    let zeros_at = |parity: usize| {
+        sample
+            .iter()
+            .skip(parity)
+            .step_by(2)
+            .filter(|&&byte| byte == 0)
+            .count()
+    };
+    let (even_zeros, odd_zeros) = (zeros_at(0), zeros_at(1));
+    // Zeros in most pairs, always on the same side
+    if odd_zeros * 10 >= pairs * 7 && even_zeros == 0 {
+        Some(TextEncoding::Utf16Le { bom: false })
+    } else if even_zeros * 10 >= pairs * 7 && odd_zeros == 0 {
+        Some(TextEncoding::Utf16Be { bom: false })
+    } else {
+        None
+    }
+}
+
+fn windows_1252_char(byte: u8) -> Option<char> {
+    match byte {
+        0x80..0xA0 => WINDOWS_1252_HIGH[usize::from(byte - 0x80)],
+        _ => Some(char::from(byte)),
+    }
+}
+
+/// Decodes file content with [`detect_encoding`], leaving out a byte order mark
+pub fn decode_text(content: &[u8]) -> Result<(String, TextEncoding), DiffError> {
+    let encoding = detect_encoding(content);
+    let invalid = || DiffError::InvalidEncoding(encoding);
+    let text = match encoding {
+        TextEncoding::Utf8 { bom } => {
+            let content = if bom {
+                &content[UTF8_BOM.len()..]
+            } else {
+                content
+            };
+            std::str::from_utf8(content)
+                .map_err(|_| invalid())?
+                .to_string()
+        }
+        TextEncoding::Utf16Le { bom } | TextEncoding::Utf16Be { bom } => {
+            let content = if bom { &content[2..] } else { content };
+            if !content.len().is_multiple_of(2) {
+                return Err(invalid());
+            }
+            let little_endian = matches!(encoding, TextEncoding::Utf16Le { .. });
+            let units = content.chunks_exact(2).map(|pair| {
+                let pair = [pair[0], pair[1]];
+                if little_endian {
+                    u16::from_le_bytes(pair)
+                } else {
+                    u16::from_be_bytes(pair)
+                }
+            });
+            char::decode_utf16(units)
+                .collect::<Result<String, _>>()
+                .map_err(|_| invalid())?
+        }
+        TextEncoding::Windows1252 => content
+            .iter()
+            .map(|&byte| windows_1252_char(byte).ok_or_else(invalid))
+            .collect::<Result<String, _>>()?,
+        TextEncoding::Latin1 => content.iter().copied().map(char::from).collect(),
+    };
+    Ok((text, encoding))
+}
+
+/// Encodes text back into `encoding`, with the byte order mark if it had one.
+///
+/// Fails on the first character the encoding can't represent rather than replacing it.
+pub fn encode_text(text: &str, encoding: TextEncoding) -> Result<Vec<u8>, DiffError> {
+    let unencodable = |character| DiffError::UnencodableCharacter {
+        character,
+        encoding,
+    };
+    let mut content = Vec::with_capacity(text.len() + 3);
+    match encoding {
+        TextEncoding::Utf8 { bom } => {
+            if bom {
+                content.extend_from_slice(UTF8_BOM);
+            }
+            content.extend_from_slice(text.as_bytes());
+        }
+        TextEncoding::Utf16Le { bom } | TextEncoding::Utf16Be { bom } => {
+            let little_endian = matches!(encoding, TextEncoding::Utf16Le { .. });
+            if bom {
+                content.extend_from_slice(if little_endian {
+                    UTF16_LE_BOM
+                } else {
+                    UTF16_BE_BOM
+                });
+            }
+            for unit in text.encode_utf16() {
+                content.extend_from_slice(&if little_endian {
+                    unit.to_le_bytes()
+                } else {
+                    unit.to_be_bytes()
+                });
+            }
+        }
+        TextEncoding::Windows1252 => {
+            for c in text.chars() {
+                let byte = match u8::try_from(c) {
+                    Ok(byte) if !(0x80..0xA0).contains(&byte) => byte,
+                    _ => (0x80..0xA0)
+                        .find(|&byte| windows_1252_char(byte) == Some(c))
+                        .ok_or_else(|| unencodable(c))?,
+                };
+                content.push(byte);
+            }
+        }
+        TextEncoding::Latin1 => {
+            for c in text.chars() {
+                content.push(u8::try_from(c).map_err(|_| unencodable(c))?);
+            }
+        }
+    }
+    Ok(content)
+}
@@ -1,12 +1,75 @@
-use regex::Regex;
+use regex::{Regex, RegexBuilder};
+use std::cell::OnceCell;
+use std::ops::Range;
+use std::path::PathBuf;
 use std::sync::OnceLock;
 use thiserror::Error;
 
 pub const REPLACE_IN_FILE_TOOL_INSTRUCTIONS: &str =
     include_str!("../replace_in_file_tool_instructions.md");
 
+pub const WRITE_TO_FILE_TOOL_INSTRUCTIONS: &str =
+    include_str!("../write_to_file_tool_instructions.md");
+
+/// Default for [`DiffOptions::max_fuzzy_line_length`]
+pub const DEFAULT_MAX_FUZZY_LINE_LENGTH: usize = 5_000;
+
+mod line_index;
+use line_index::LineIndex;
+
+mod atomic_write;
+
+pub mod encoding;
+pub use encoding::{TextEncoding, decode_text, detect_encoding, encode_text};
+
+pub mod apply_file;
+pub use apply_file::{ApplyOutcome, apply_to_path};
+
+pub mod workspace;
+pub use workspace::{DEFAULT_DENIED_PATHS, Workspace};
+
+pub mod limits;
+pub use limits::Limits;
+
+pub mod precondition;
+pub use precondition::{Precondition, content_sha256};
+
 pub mod lib_v1;
-pub use lib_v1::construct_new_file_content_v1;
+pub use lib_v1::{construct_new_file_content_v1, construct_new_file_content_v1_with_options};
+
+pub mod bytes;
+pub use bytes::{
+    construct_new_file_content_v1_bytes, construct_new_file_content_v2_bytes,
+    construct_new_file_content_v2_bytes_with_options,
+};
+
+pub mod markers;
+pub use markers::{BlockKind, BlockModifiers, MarkerSet, Occurrence};
+
+pub mod xml_edits;
+pub use xml_edits::{
+    construct_new_file_content_xml, construct_new_file_content_xml_with_options,
+    parse_xml_edit_blocks,
+};
+
+pub mod str_replace;
+pub use str_replace::{
+    StrReplaceOutcome, StrReplaceRequest, str_replace, str_replace_with_options,
+};
+
+pub mod editor_tool;
+pub use editor_tool::{EditorCommand, EditorTool};
+
+pub mod compose;
+pub use compose::{BlockDependency, Composition, compose_diffs};
+
+pub mod rebase;
+pub use rebase::{RebaseConflict, RebaseOutcome, rebase_diff};
+
+pub mod write_to_file;
+pub use write_to_file::{
+    WriteToFileOutcome, detect_truncation, search_replace_equivalent, write_to_file,
+};
 
 #[derive(Error, Debug)]
 pub enum DiffError {
@@ -16,6 +79,26 @@ pub enum DiffError {
     #[error("The SEARCH block:\n{0}\n...matched an incorrect content in the file.")]
     SearchBlockIncorrectMatch(String),
 
+    #[error(
+        "The SEARCH block:\n{search}\n...occurs {found} time(s), so occurrence #{occurrence} does not exist."
+    )]
+    OccurrenceNotFound {
+        search: String,
+        occurrence: usize,
+        found: usize,
+    },
+
+    #[error("Invalid regular expression in SEARCH block: {0}")]
+    InvalidRegex(String),
+
+    #[error("Invalid SEARCH block modifiers: {0}")]
+    InvalidBlockModifiers(String),
+
+    #[error(
+        "SEARCH/REPLACE blocks {first} and {second} (0-based, in diff order) replace overlapping parts of the file"
+    )]
+    OverlappingBlocks { first: usize, second: usize },
+
     #[error(
         "Invalid state transition.\nValid transitions are:\n- Idle → StateSearch\n- StateSearch → StateReplace"
     )]
@@ -39,174 +122,364 @@ pub enum DiffError {
         "File processing incomplete - SEARCH/REPLACE operations still active during finalization"
     )]
     ProcessingIncomplete,
-}
 
-const SEARCH_BLOCK_START: &str = "------- SEARCH";
-const SEARCH_BLOCK_END: &str = "=======";
-const REPLACE_BLOCK_END: &str = "+++++++ REPLACE";
+    #[error("Malformed XML edit block: {0}")]
+    MalformedXmlEdit(String),
 
-const SEARCH_BLOCK_CHAR: &str = "-";
-const REPLACE_BLOCK_CHAR: &str = "+";
-const LEGACY_SEARCH_BLOCK_CHAR: &str = "<";
-const LEGACY_REPLACE_BLOCK_CHAR: &str = ">";
+    #[error("No replacement was performed, old_str `{old_str}` did not appear verbatim in {path}.")]
+    OldStrNotFound { path: String, old_str: String },
 
-fn search_block_start_regex() -> &'static Regex {
-    static REGEX: OnceLock<Regex> = OnceLock::new();
-    REGEX.get_or_init(|| Regex::new(r"^[-]{3,} SEARCH>?$").unwrap())
-}
+    #[error(
+        "No replacement was performed. Multiple occurrences of old_str `{old_str}` in lines {lines:?}. Please ensure it is unique."
+    )]
+    OldStrNotUnique {
+        path: String,
+        old_str: String,
+        lines: Vec<usize>,
+    },
 
-fn legacy_search_block_start_regex() -> &'static Regex {
-    static REGEX: OnceLock<Regex> = OnceLock::new();
-    REGEX.get_or_init(|| Regex::new(r"^[<]{3,} SEARCH>?$").unwrap())
-}
+    #[error("No replacement was performed. `new_str` and `old_str` must be different.")]
+    OldStrEqualsNewStr,
 
-fn search_block_end_regex() -> &'static Regex {
-    static REGEX: OnceLock<Regex> = OnceLock::new();
-    REGEX.get_or_init(|| Regex::new(r"^[=]{3,}$").unwrap())
-}
+    #[error("Parameter `old_str` must not be empty.")]
+    EmptyOldStr,
 
-fn replace_block_end_regex() -> &'static Regex {
-    static REGEX: OnceLock<Regex> = OnceLock::new();
-    REGEX.get_or_init(|| Regex::new(r"^[+]{3,} REPLACE>?$").unwrap())
-}
+    #[error("The path {} does not exist. Please provide a valid path.", .0.display())]
+    PathNotFound(PathBuf),
 
-fn legacy_replace_block_end_regex() -> &'static Regex {
-    static REGEX: OnceLock<Regex> = OnceLock::new();
-    REGEX.get_or_init(|| Regex::new(r"^[>]{3,} REPLACE>?$").unwrap())
-}
+    #[error("File already exists at: {}. Cannot overwrite files using command `create`.", .0.display())]
+    FileAlreadyExists(PathBuf),
 
-fn is_search_block_start(line: &str) -> bool {
-    search_block_start_regex().is_match(line) || legacy_search_block_start_regex().is_match(line)
-}
+    #[error("Invalid `view_range` parameter: {0}")]
+    InvalidViewRange(String),
+
+    #[error(
+        "Invalid `insert_line` parameter: {line}. It should be within the range of lines of the file: [0, {line_count}]"
+    )]
+    InvalidInsertLine { line: usize, line_count: usize },
+
+    #[error("No edit history found for {}.", .0.display())]
+    NoEditHistory(PathBuf),
 
-fn is_search_block_end(line: &str) -> bool {
-    search_block_end_regex().is_match(line)
+    #[error("Refusing to write {}: {reason}. Provide the complete file content.", path.display())]
+    SuspectedTruncation { path: PathBuf, reason: String },
+
+    #[error("The file content is not valid {0}.")]
+    InvalidEncoding(TextEncoding),
+
+    #[error(
+        "The character {character:?} can't be written to a {encoding} file. Use characters the file's encoding supports."
+    )]
+    UnencodableCharacter {
+        character: char,
+        encoding: TextEncoding,
+    },
+
+    #[error(
+        "The file looks binary (it contains NUL bytes), so it can't be edited with SEARCH/REPLACE blocks. Use another tool to change it."
+    )]
+    BinaryContent,
+
+    #[error(
+        "The SEARCH block:\n{search}\n...does not match the file exactly. The file has lines longer than {max_line_length} bytes (minified or generated code), so only exact matches are allowed. Copy the exact text, or regenerate the file instead of editing it."
+    )]
+    ExactMatchRequired {
+        search: String,
+        max_line_length: usize,
+    },
+
+    #[error(
+        "The diff is {size} bytes, more than the limit of {limit}. Split it into smaller diffs."
+    )]
+    DiffTooLarge { size: usize, limit: usize },
+
+    #[error("The diff has more than {limit} SEARCH/REPLACE blocks. Split it into smaller diffs.")]
+    TooManyBlocks { limit: usize },
+
+    #[error("The file is {size} bytes, more than the limit of {limit} for editing.")]
+    FileTooLarge { size: usize, limit: usize },
+
+    #[error(
+        "The SEARCH block:\n{search}\n...would remove {deleted} of the {file_size} bytes of the file, more than allowed for a single block. Use smaller blocks that keep the surrounding content."
+    )]
+    BlockDeletesTooMuch {
+        search: String,
+        deleted: usize,
+        file_size: usize,
+    },
+
+    #[error(
+        "An empty SEARCH section would replace the whole file, which is not allowed here. Use SEARCH blocks matching the parts to change."
+    )]
+    WholeFileRewriteForbidden,
+
+    #[error("The path {} is outside the workspace.", .0.display())]
+    PathOutsideWorkspace(PathBuf),
+
+    #[error(
+        "The path {} leads through a symlink to {}, outside the workspace.",
+        path.display(),
+        target.display()
+    )]
+    SymlinkOutsideWorkspace { path: PathBuf, target: PathBuf },
+
+    #[error("Access to {} is not allowed (matches `{pattern}`).", path.display())]
+    DeniedPath { path: PathBuf, pattern: String },
+
+    #[error(
+        "The file changed since it was read (expected {expected}, found {actual}). Read it again and make the edit against its current content."
+    )]
+    StaleOriginal { expected: String, actual: String },
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
 }
 
-fn is_replace_block_end(line: &str) -> bool {
-    replace_block_end_regex().is_match(line) || legacy_replace_block_end_regex().is_match(line)
+/// Splits search content into lines, without the empty line after its final newline
+fn search_lines(search_content: &str) -> Vec<&str> {
+    let mut search_lines: Vec<&str> = search_content.split('\n').collect();
+    if search_lines.last().is_some_and(|l| l.is_empty()) {
+        search_lines.pop();
+    }
+    search_lines
 }
 
 /// Attempts a line-trimmed fallback match
-fn line_trimmed_fallback_match(
-    original_content: &str,
+pub(crate) fn line_trimmed_fallback_match(
+    lines: &LineIndex,
     search_content: &str,
     start_index: usize,
 ) -> Option<(usize, usize)> {
-    let original_lines: Vec<&str> = original_content.split('\n').collect();
-    let mut search_lines: Vec<&str> = search_content.split('\n').collect();
-
-    // Trim trailing empty line if exists
-    if search_lines.last().is_some_and(|l| l.is_empty()) {
-        search_lines.pop();
+    let search_lines = search_lines(search_content);
+    if search_lines.is_empty() || search_lines.len() > lines.len() {
+        return None;
     }
 
-    if search_lines.is_empty() {
+    let start_line_num = lines.first_line_from(start_index);
+    let last_start_line_num = lines.len() - search_lines.len();
+
+    // Only look where the rarest search line occurs, then check the lines around it
+    let (pivot, candidates) = search_lines
+        .iter()
+        .enumerate()
+        .map(|(j, line)| (j, lines.lines_trimmed_to(line.trim())))
+        .min_by_key(|(_, candidates)| candidates.len())?;
+    let skip = candidates.partition_point(|&n| n < start_line_num + pivot);
+
+    candidates[skip..]
+        .iter()
+        .map(|&n| n - pivot)
+        .take_while(|&i| i <= last_start_line_num)
+        .find(|&i| {
+            search_lines
+                .iter()
+                .enumerate()
+                .all(|(j, search_line)| lines.line(i + j).trim() == search_line.trim())
+        })
+        .map(|i| lines.byte_range(i, search_lines.len()))
+}
+
+/// Attempts to match blocks using first and last lines as anchors
+pub(crate) fn block_anchor_fallback_match(
+    lines: &LineIndex,
+    search_content: &str,
+    start_index: usize,
+) -> Option<(usize, usize)> {
+    // Only use this approach for blocks of 3+ lines
+    if search_content.split('\n').count() < 3 {
         return None;
     }
 
-    // Find the line number where start_index falls
-    let mut start_line_num = 0;
-    let mut current_index = 0;
-    while current_index < start_index && start_line_num < original_lines.len() {
-        current_index += original_lines[start_line_num].len() + 1; // +1 for \n
-        start_line_num += 1;
+    let search_lines = search_lines(search_content);
+    if search_lines.len() > lines.len() {
+        return None;
     }
 
-    // For each possible starting position in original content
-    for i in start_line_num..=original_lines.len().saturating_sub(search_lines.len()) {
-        let mut matches = true;
-
-        // Try to match all search lines from this position
-        for (j, search_line) in search_lines.iter().enumerate() {
-            let original_trimmed = original_lines[i + j].trim();
-            let search_trimmed = search_line.trim();
+    let first_line_search = search_lines[0].trim();
+    let last_line_search = search_lines[search_lines.len() - 1].trim();
+    let search_block_size = search_lines.len();
 
-            if original_trimmed != search_trimmed {
-                matches = false;
-                break;
-            }
-        }
+    let start_line_num = lines.first_line_from(start_index);
+    let candidates = lines.lines_trimmed_to(first_line_search);
+    let skip = candidates.partition_point(|&i| i < start_line_num);
+
+    // Check if the last line matches at the expected position of each first line
+    candidates[skip..]
+        .iter()
+        .copied()
+        .take_while(|&i| i + search_block_size <= lines.len())
+        .find(|&i| lines.line(i + search_block_size - 1).trim() == last_line_search)
+        .map(|i| lines.byte_range(i, search_block_size))
+}
 
-        // If we found a match, calculate the exact character positions
-        if matches {
-            // Find start character index
-            let mut match_start_index = 0;
-            for k in 0..i {
-                match_start_index += original_lines[k].len() + 1; // +1 for \n
-            }
+fn elision_marker_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r"^\s*(?:(?://|#|--|;|/\*|<!--)\s*)?(?:\.{3}|…)(?:[^.…]*(?:\.{3}|…))?\s*(?:\*/|-->)?\s*$",
+        )
+        .unwrap()
+    })
+}
 
-            // Find end character index
-            let mut match_end_index = match_start_index;
-            for k in 0..search_lines.len() {
-                match_end_index += original_lines[i + k].len() + 1; // +1 for \n
-            }
+/// Returns true if the line stands for omitted content, e.g. `...` or `// ... existing code ...`
+pub(crate) fn is_elision_marker(line: &str) -> bool {
+    elision_marker_regex().is_match(line)
+}
 
-            return Some((match_start_index, match_end_index));
-        }
-    }
+/// Returns true if the original lines starting at `start` match `segment` after trimming
+fn segment_matches_at(lines: &LineIndex, start: usize, segment: &[&str]) -> bool {
+    start + segment.len() <= lines.len()
+        && segment
+            .iter()
+            .enumerate()
+            .all(|(j, line)| lines.line(start + j).trim() == line.trim())
+}
 
-    None
+/// The first line at or after `from` where `segment` matches
+fn find_segment(lines: &LineIndex, segment: &[&str], from: usize) -> Option<usize> {
+    let candidates = lines.lines_trimmed_to(segment[0].trim());
+    let skip = candidates.partition_point(|&i| i < from);
+    candidates[skip..]
+        .iter()
+        .copied()
+        .find(|&i| segment_matches_at(lines, i, segment))
 }
 
-/// Attempts to match blocks using first and last lines as anchors
-fn block_anchor_fallback_match(
-    original_content: &str,
+/// Attempts to match blocks whose SEARCH content elides lines with a `...` marker.
+///
+/// The lines around each marker are matched (line-trimmed) in order and everything
+/// between them is treated as part of the match, so the whole span gets replaced.
+fn elided_block_fallback_match(
+    lines: &LineIndex,
     search_content: &str,
     start_index: usize,
 ) -> Option<(usize, usize)> {
-    let original_lines: Vec<&str> = original_content.split('\n').collect();
-    let mut search_lines: Vec<&str> = search_content.split('\n').collect();
+    let search_lines = search_lines(search_content);
 
-    // Only use this approach for blocks of 3+ lines
-    if search_lines.len() < 3 {
+    // A gap needs anchors on both sides, otherwise its extent is unknown
+    if search_lines.first().is_none_or(|l| is_elision_marker(l))
+        || search_lines.last().is_none_or(|l| is_elision_marker(l))
+    {
         return None;
     }
 
-    // Trim trailing empty line if exists
-    if search_lines.last().is_some_and(|l| l.is_empty()) {
-        search_lines.pop();
+    let segments: Vec<&[&str]> = search_lines
+        .split(|l| is_elision_marker(l))
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    if segments.len() < 2 {
+        return None;
     }
 
-    let first_line_search = search_lines[0].trim();
-    let last_line_search = search_lines[search_lines.len() - 1].trim();
-    let search_block_size = search_lines.len();
+    let start_line_num = lines.first_line_from(start_index);
+    let first = find_segment(lines, segments[0], start_line_num)?;
 
-    // Find the line number where start_index falls
-    let mut start_line_num = 0;
-    let mut current_index = 0;
-    while current_index < start_index && start_line_num < original_lines.len() {
-        current_index += original_lines[start_line_num].len() + 1;
-        start_line_num += 1;
+    // Place every following segment at its earliest position after the previous one;
+    // if one can't be placed, it can't appear after a later start either
+    let mut next_line = first + segments[0].len();
+    for segment in &segments[1..] {
+        next_line = find_segment(lines, segment, next_line)? + segment.len();
     }
 
-    // Look for matching start and end anchors
-    for i in start_line_num..=original_lines.len().saturating_sub(search_block_size) {
-        // Check if first line matches
-        if original_lines[i].trim() != first_line_search {
-            continue;
-        }
+    Some(lines.byte_range(first, next_line - first))
+}
 
-        // Check if last line matches at the expected position
-        if original_lines[i + search_block_size - 1].trim() != last_line_search {
-            continue;
-        }
+/// How a SEARCH block was located in the original content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchStrategy {
+    Exact,
+    LineTrimmed,
+    BlockAnchor,
+    Elided,
+    /// The SEARCH content is a regular expression
+    Regex,
+}
 
-        // Calculate exact character positions
-        let mut match_start_index = 0;
-        for k in 0..i {
-            match_start_index += original_lines[k].len() + 1;
-        }
+type FallbackMatcher = fn(&LineIndex, &str, usize) -> Option<(usize, usize)>;
 
-        let mut match_end_index = match_start_index;
-        for k in 0..search_block_size {
-            match_end_index += original_lines[i + k].len() + 1;
-        }
+/// Locates search content at or after `start_index`, trying an exact match first and
+/// then the line-based fallbacks from strictest to loosest
+pub(crate) fn find_search_match(
+    lines: &LineIndex,
+    search_content: &str,
+    start_index: usize,
+) -> Option<(usize, usize, MatchStrategy)> {
+    // A start inside a character can't be the start of an exact match
+    let rest = lines.content().get(start_index..).unwrap_or_default();
+    if let Some(exact_index) = rest.find(search_content) {
+        let exact_index = start_index + exact_index;
+        return Some((
+            exact_index,
+            exact_index + search_content.len(),
+            MatchStrategy::Exact,
+        ));
+    }
+
+    find_fuzzy_match(lines, search_content, start_index)
+}
 
-        return Some((match_start_index, match_end_index));
+/// Tries the line-based fallbacks from strictest to loosest
+fn find_fuzzy_match(
+    lines: &LineIndex,
+    search_content: &str,
+    start_index: usize,
+) -> Option<(usize, usize, MatchStrategy)> {
+    let fallbacks: [(FallbackMatcher, MatchStrategy); 3] = [
+        (line_trimmed_fallback_match, MatchStrategy::LineTrimmed),
+        // Try block anchor fallback for larger blocks
+        (block_anchor_fallback_match, MatchStrategy::BlockAnchor),
+        // Elided SEARCH blocks span everything between their anchors
+        (elided_block_fallback_match, MatchStrategy::Elided),
+    ];
+    fallbacks.into_iter().find_map(|(fallback, strategy)| {
+        fallback(lines, search_content, start_index).map(|(start, end)| (start, end, strategy))
+    })
+}
+
+/// Finds every non-overlapping match of the search content at or after `start_index`,
+/// preferring exact occurrences and only falling back to fuzzy matching when there is none
+pub(crate) fn find_all_search_matches(
+    lines: &LineIndex,
+    search_content: &str,
+    start_index: usize,
+) -> (Vec<(usize, usize)>, Option<MatchStrategy>) {
+    let original_content = lines.content();
+    let exact: Vec<(usize, usize)> = original_content
+        .get(start_index..)
+        .unwrap_or_default()
+        .match_indices(search_content)
+        .map(|(start, matched)| (start_index + start, start_index + start + matched.len()))
+        .collect();
+    if !exact.is_empty() {
+        return (exact, Some(MatchStrategy::Exact));
     }
 
-    None
+    let mut matches = Vec::new();
+    let mut strategy = None;
+    let mut start_index = start_index;
+    while start_index < original_content.len() {
+        let Some((start, end, found_with)) = find_fuzzy_match(lines, search_content, start_index)
+        else {
+            break;
+        };
+        if end <= start {
+            break;
+        }
+        matches.push((start, end));
+        strategy.get_or_insert(found_with);
+        start_index = end;
+    }
+
+    (matches, strategy)
+}
+
+/// Refuses content that looks binary, where matching lines of text is meaningless
+pub(crate) fn ensure_text_content(content: &str) -> Result<(), DiffError> {
+    if content.contains('\0') {
+        return Err(DiffError::BinaryContent);
+    }
+    Ok(())
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -216,42 +489,340 @@ enum ProcessingState {
     StateReplace = 1 << 1,
 }
 
-struct NewFileContentConstructor {
-    original_content: String,
+struct NewFileContentConstructor<'a> {
+    markers: &'a MarkerSet,
+    limits: &'a Limits,
+    max_fuzzy_line_length: usize,
+    original_content: &'a str,
+    /// Line index of the original content, built the first time a block needs it
+    line_index: OnceCell<LineIndex<'a>>,
     is_final: bool,
     state: u8,
     pending_non_standard_lines: Vec<String>,
-    result: String,
+    /// Replacements made by finished blocks, sorted by position and non-overlapping
+    splices: Vec<Splice>,
+    reports: Vec<BlockReport>,
     last_processed_index: usize,
     current_search_content: String,
-    search_match_index: isize,
-    search_end_index: isize,
+    /// Ranges of the original content replaced by the block in progress
+    current_matches: Vec<Range<usize>>,
+    current_strategy: Option<MatchStrategy>,
+    /// Compiled SEARCH content of a `REGEX` block in progress
+    current_regex: Option<Regex>,
+    awaiting_replace_start: bool,
+    current_replace_content: String,
+    current_modifiers: BlockModifiers,
+    blocks: Vec<EditBlock>,
+    /// Only collect blocks, without matching them against the original content
+    parse_only: bool,
 }
 
-impl NewFileContentConstructor {
-    fn new(original_content: String, is_final: bool) -> Self {
+impl<'a> NewFileContentConstructor<'a> {
+    fn new(original_content: &'a str, is_final: bool, options: &'a DiffOptions) -> Self {
         Self {
+            markers: &options.markers,
+            limits: &options.limits,
+            max_fuzzy_line_length: options.max_fuzzy_line_length,
             original_content,
+            line_index: OnceCell::new(),
             is_final,
             state: ProcessingState::Idle as u8,
             pending_non_standard_lines: Vec::new(),
-            result: String::new(),
+            splices: Vec::new(),
+            reports: Vec::new(),
             last_processed_index: 0,
             current_search_content: String::new(),
-            search_match_index: -1,
-            search_end_index: -1,
+            current_matches: Vec::new(),
+            current_strategy: None,
+            current_regex: None,
+            awaiting_replace_start: false,
+            current_replace_content: String::new(),
+            current_modifiers: BlockModifiers::default(),
+            blocks: Vec::new(),
+            parse_only: false,
+        }
+    }
+
+    fn new_parse_only(options: &'a DiffOptions) -> Self {
+        Self {
+            parse_only: true,
+            ..Self::new("", true, options)
         }
     }
 
+    fn line_index(&self) -> &LineIndex<'a> {
+        self.line_index
+            .get_or_init(|| LineIndex::new(self.original_content, self.max_fuzzy_line_length))
+    }
+
     fn reset_for_next_block(&mut self) {
         self.state = ProcessingState::Idle as u8;
         self.current_search_content.clear();
-        self.search_match_index = -1;
-        self.search_end_index = -1;
+        self.current_replace_content.clear();
+        self.current_modifiers = BlockModifiers::default();
+        self.current_matches.clear();
+        self.current_strategy = None;
+        self.current_regex = None;
+        self.awaiting_replace_start = false;
+    }
+
+    fn push_search_line(&mut self, line: &str) {
+        self.current_search_content.push_str(line);
+        self.current_search_content.push('\n');
+    }
+
+    fn push_replace_line(&mut self, line: &str) {
+        self.current_replace_content.push_str(line);
+        self.current_replace_content.push('\n');
+    }
+
+    fn finish_block(&mut self) -> Result<(), DiffError> {
+        self.limits.check_block_count(self.blocks.len())?;
+        if !self.current_matches.is_empty() {
+            let modifiers = &self.current_modifiers;
+            let (splices, processed_end) = if modifiers.move_text {
+                // The source stays the position later blocks continue from
+                (self.move_splices()?, Some(self.current_matches[0].end))
+            } else {
+                let splices: Vec<Splice> = self.current_splices().collect();
+                self.limits.check_deletion(
+                    &self.current_search_content,
+                    splices
+                        .iter()
+                        .map(|s| (&self.original_content[s.range.clone()], &*s.replacement)),
+                    self.original_content.len(),
+                )?;
+                let advances = !modifiers.whole_file
+                    && !matches!(modifiers.kind, BlockKind::Prepend | BlockKind::Append);
+                let end = splices.iter().map(|s| s.range.end).max();
+                (splices, end.filter(|_| advances))
+            };
+            if let Some(end) = processed_end {
+                self.last_processed_index = self.last_processed_index.max(end);
+            }
+            self.reports.push(BlockReport {
+                ranges: splices.iter().map(|s| s.range.clone()).collect(),
+                strategy: self.current_strategy,
+            });
+            for splice in splices {
+                let index = self.splices.partition_point(|s| s.key() <= splice.key());
+                self.splices.insert(index, splice);
+            }
+            self.current_matches.clear();
+        }
+        self.blocks.push(EditBlock {
+            search: std::mem::take(&mut self.current_search_content),
+            replace: std::mem::take(&mut self.current_replace_content),
+            modifiers: std::mem::take(&mut self.current_modifiers),
+        });
+        self.reset_for_next_block();
+        Ok(())
+    }
+
+    /// The edits of a `MOVE` block: removing the matched source and inserting the very
+    /// same bytes at the destination, in file order.
+    ///
+    /// A line break is added after moved text that doesn't end with one, so it can't be
+    /// glued to the line that follows at its destination.
+    fn move_splices(&self) -> Result<Vec<Splice>, DiffError> {
+        let content = &self.original_content;
+        let source = &self.current_matches[0];
+        let source = source.start..source.end.min(content.len());
+        let anchor = &self.current_replace_content;
+
+        let destination = match self.current_modifiers.kind {
+            BlockKind::Prepend | BlockKind::Append if !anchor.is_empty() => {
+                return Err(DiffError::InvalidBlockModifiers(
+                    "MOVE PREPEND and MOVE APPEND blocks must have an empty REPLACE section"
+                        .to_string(),
+                ));
+            }
+            BlockKind::Prepend => 0..0,
+            BlockKind::Append => content.len()..content.len(),
+            BlockKind::InsertBefore | BlockKind::InsertAfter => {
+                if anchor.is_empty() {
+                    return Err(DiffError::InvalidBlockModifiers(
+                        "MOVE BEFORE and MOVE AFTER blocks need a destination anchor in the REPLACE section"
+                            .to_string(),
+                    ));
+                }
+                let (matches, _) = find_all_search_matches(self.line_index(), anchor, 0);
+                let anchor_range = matches
+                    .into_iter()
+                    .map(|(start, end)| start..end)
+                    .find(|range| {
+                        !self.overlaps_splice(range)
+                            && (range.end <= source.start || source.end <= range.start)
+                    })
+                    .ok_or_else(|| self.not_found(anchor))?;
+                self.edit_range(&anchor_range)
+            }
+            BlockKind::Replace => {
+                return Err(DiffError::InvalidBlockModifiers(
+                    "MOVE blocks need a destination: BEFORE, AFTER, PREPEND or APPEND".to_string(),
+                ));
+            }
+        };
+        if source.start < destination.start && destination.start < source.end
+            || self.overlaps_splice(&destination)
+        {
+            return Err(DiffError::SearchBlockIncorrectMatch(
+                anchor.trim_end().to_string(),
+            ));
+        }
+
+        let mut moved = content[source.clone()].to_string();
+        if !moved.ends_with('\n') {
+            moved.push('\n');
+        }
+        if destination.start == content.len() && !content.is_empty() && !content.ends_with('\n') {
+            moved.insert(0, '\n');
+        }
+
+        let mut splices = vec![
+            Splice {
+                range: source,
+                replacement: String::new(),
+            },
+            Splice {
+                range: destination,
+                replacement: moved,
+            },
+        ];
+        splices.sort_by_key(Splice::key);
+        Ok(splices)
+    }
+
+    /// The edits made by the block in progress, one per match
+    fn current_splices(&self) -> impl Iterator<Item = Splice> + '_ {
+        self.current_matches.iter().map(|matched| {
+            let range = self.edit_range(matched);
+            let mut replacement = self.current_replacement(matched);
+            // Inserted lines must not be glued to a last line without a line break
+            if self.current_modifiers.kind.is_insertion()
+                && range.start == self.original_content.len()
+                && !self.original_content.is_empty()
+                && !self.original_content.ends_with('\n')
+            {
+                replacement.insert(0, '\n');
+            }
+            Splice { range, replacement }
+        })
+    }
+
+    /// The range of the original content edited for a match, which is an empty range at
+    /// a line boundary for insertions
+    fn edit_range(&self, matched: &Range<usize>) -> Range<usize> {
+        let content = &self.original_content;
+        let point = match self.current_modifiers.kind {
+            BlockKind::Replace => return matched.clone(),
+            BlockKind::InsertBefore => content[..matched.start].rfind('\n').map_or(0, |i| i + 1),
+            BlockKind::InsertAfter => {
+                let end = matched.end.min(content.len());
+                if end == 0 || content[..end].ends_with('\n') {
+                    end
+                } else {
+                    content[end..]
+                        .find('\n')
+                        .map_or(content.len(), |i| end + i + 1)
+                }
+            }
+            BlockKind::Prepend | BlockKind::Append => matched.start,
+        };
+        point..point
     }
 
-    fn find_last_matching_line_index(&self, regex: &Regex, line_limit: usize) -> Option<usize> {
-        (0..line_limit).rev().find(|&i| regex.is_match(&self.pending_non_standard_lines[i]))
+    /// The text replacing a match of the block in progress, with capture groups expanded
+    /// for `REGEX` blocks
+    fn current_replacement(&self, matched: &Range<usize>) -> String {
+        let Some(regex) = &self.current_regex else {
+            return self.current_replace_content.clone();
+        };
+        let template = self
+            .current_replace_content
+            .strip_suffix('\n')
+            .unwrap_or(&self.current_replace_content);
+        let mut replacement = String::new();
+        if let Some(captures) = regex.captures_at(self.original_content, matched.start) {
+            captures.expand(template, &mut replacement);
+        }
+        if self.current_modifiers.kind.is_insertion() {
+            // Inserted content is line-based like the other block kinds
+            replacement.push('\n');
+        }
+        replacement
+    }
+
+    /// Renders the original content with all replacements made so far.
+    ///
+    /// Unless this is the final chunk, output stops after the replacement being streamed
+    /// (or the last processed position), since the rest may still change.
+    fn render(&self) -> String {
+        // A move is only shown once its destination is known
+        let current: Vec<Splice> = if self.current_modifiers.move_text {
+            Vec::new()
+        } else {
+            self.current_splices().collect()
+        };
+        // Blocks resolved before the last processed position don't cut the preview short
+        let frontier = current
+            .iter()
+            .map(|s| s.range.end)
+            .max()
+            .map_or(self.last_processed_index, |end| {
+                end.max(self.last_processed_index)
+            });
+        let mut splices: Vec<Splice> = self.splices.iter().cloned().chain(current).collect();
+        splices.sort_by_key(Splice::key);
+
+        let mut result = String::with_capacity(self.original_content.len());
+        let mut cursor = 0;
+        for splice in &splices {
+            if !self.is_final && splice.range.end > frontier {
+                break;
+            }
+            result.push_str(&self.original_content[cursor..splice.range.start]);
+            result.push_str(&splice.replacement);
+            cursor = splice.range.end;
+        }
+
+        let end = if self.is_final {
+            self.original_content.len()
+        } else {
+            frontier.min(self.original_content.len())
+        };
+        if cursor < end {
+            result.push_str(&self.original_content[cursor..end]);
+        }
+        result
+    }
+
+    /// Feeds an already parsed block through the same state transitions as its diff lines
+    fn process_block(&mut self, block: &EditBlock) -> Result<(), DiffError> {
+        self.activate_search_state()?;
+        self.current_modifiers = block.modifiers.clone();
+        for line in content_lines(&block.search) {
+            self.push_search_line(line);
+        }
+        self.activate_replace_state()?;
+        self.before_replace()?;
+        for line in content_lines(&block.replace) {
+            self.push_replace_line(line);
+        }
+        self.finish_block()?;
+        Ok(())
+    }
+
+    fn find_last_matching_line_index(
+        &self,
+        is_match: impl Fn(&MarkerSet, &str) -> bool,
+        line_limit: usize,
+    ) -> Option<usize> {
+        (0..line_limit).rev().find(|&i| {
+            self.pending_non_standard_lines
+                .get(i)
+                .is_some_and(|line| is_match(self.markers, line))
+        })
     }
 
     fn update_processing_state(&mut self, new_state: ProcessingState) -> Result<(), DiffError> {
@@ -291,8 +862,17 @@ impl NewFileContentConstructor {
     }
 
     fn has_pending_non_standard_lines(&self, pending_non_standard_line_limit: usize) -> bool {
-        self.pending_non_standard_lines.len() - pending_non_standard_line_limit
-            < self.pending_non_standard_lines.len()
+        pending_non_standard_line_limit > 0 && !self.pending_non_standard_lines.is_empty()
+    }
+
+    /// Copies pending lines for re-processing, failing instead of panicking when earlier
+    /// repairs left fewer lines than the range expects
+    fn pending_lines(&self, range: Range<usize>) -> Result<Vec<String>, DiffError> {
+        self.pending_non_standard_lines
+            .get(range)
+            .filter(|lines| !lines.is_empty())
+            .map(<[String]>::to_vec)
+            .ok_or(DiffError::NoLinesAvailable)
     }
 
     pub fn process_line(&mut self, line: String) -> Result<(), DiffError> {
@@ -301,25 +881,64 @@ impl NewFileContentConstructor {
         Ok(())
     }
 
-    pub fn get_result(mut self) -> Result<String, DiffError> {
+    pub fn get_result(self) -> Result<String, DiffError> {
+        self.into_report().map(|report| report.content)
+    }
+
+    fn into_report(mut self) -> Result<DiffReport, DiffError> {
+        self.finish_diff()?;
+        Ok(DiffReport {
+            content: self.render(),
+            blocks: self.reports,
+        })
+    }
+
+    /// The replacements made by each block that matched, in diff order
+    fn into_block_splices(mut self) -> Result<Vec<Vec<Splice>>, DiffError> {
+        self.finish_diff()?;
+        // Splices at the same position are kept in block order, so taking the first one
+        // with a block's range gives that block's
+        let mut remaining: Vec<Option<Splice>> = self.splices.into_iter().map(Some).collect();
+        Ok(self
+            .reports
+            .iter()
+            .map(|report| {
+                report
+                    .ranges
+                    .iter()
+                    .filter_map(|range| {
+                        remaining
+                            .iter_mut()
+                            .find(|splice| splice.as_ref().is_some_and(|s| s.range == *range))
+                            .and_then(Option::take)
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+
+    fn finish_diff(&mut self) -> Result<(), DiffError> {
         // Handle the case where we're still in replace mode when processing ends
         // and this is the final chunk - treat it as if we encountered the REPLACE marker
-        if self.is_final && self.is_replacing_active() && self.search_match_index != -1 {
+        if self.is_final && self.is_replacing_active() && !self.current_matches.is_empty() {
             // Finalize the current replacement
-            self.last_processed_index = self.search_end_index as usize;
-            self.reset_for_next_block();
+            self.finish_block()?;
         }
 
-        // If this is the final chunk, append any remaining original content
-        if self.is_final && self.last_processed_index < self.original_content.len() {
-            self.result
-                .push_str(&self.original_content[self.last_processed_index..]);
+        if self.is_final && self.state != ProcessingState::Idle as u8 {
+            return Err(DiffError::ProcessingIncomplete);
         }
+        Ok(())
+    }
 
-        if self.is_final && self.state != ProcessingState::Idle as u8 {
+    fn into_blocks(mut self) -> Result<Vec<EditBlock>, DiffError> {
+        if self.is_replacing_active() {
+            self.finish_block()?;
+        }
+        if self.state != ProcessingState::Idle as u8 {
             return Err(DiffError::ProcessingIncomplete);
         }
-        Ok(self.result)
+        Ok(self.blocks)
     }
 
     fn internal_process_line(
@@ -330,7 +949,7 @@ impl NewFileContentConstructor {
     ) -> Result<usize, DiffError> {
         let mut remove_line_count = 0;
 
-        if is_search_block_start(&line) {
+        if let Some(modifiers) = self.markers.parse_search_block_start(&line) {
             remove_line_count = self
                 .trim_pending_non_standard_trailing_empty_lines(pending_non_standard_line_limit);
             if remove_line_count > 0 {
@@ -344,7 +963,8 @@ impl NewFileContentConstructor {
                 }
             }
             self.activate_search_state()?;
-        } else if is_search_block_end(&line) {
+            self.current_modifiers = modifiers;
+        } else if self.markers.is_search_block_end(&line) {
             // 校验非标内容
             if !self.is_searching_active() {
                 self.try_fix_search_block(pending_non_standard_line_limit)?;
@@ -353,25 +973,26 @@ impl NewFileContentConstructor {
                 }
             }
             self.activate_replace_state()?;
+            self.awaiting_replace_start = self.markers.has_replace_block_start();
             self.before_replace()?;
-        } else if is_replace_block_end(&line) {
+        } else if self.markers.is_replace_block_end(&line) {
             if !self.is_replacing_active() {
                 self.try_fix_replace_block(pending_non_standard_line_limit)?;
                 if can_write_pending_non_standard_lines {
                     self.pending_non_standard_lines.clear();
                 }
             }
-            self.last_processed_index = self.search_end_index as usize;
-            self.reset_for_next_block();
+            self.finish_block()?;
+        } else if self.awaiting_replace_start
+            && (line.trim().is_empty() || self.markers.is_replace_block_start(&line))
+        {
+            // Skip the optional REPLACE opening marker and blank lines before it
+            self.awaiting_replace_start = !self.markers.is_replace_block_start(&line);
         } else if self.is_replacing_active() {
-            // Output replacement lines immediately if we know the insertion point
-            if self.search_match_index != -1 {
-                self.result.push_str(&line);
-                self.result.push('\n');
-            }
+            self.awaiting_replace_start = false;
+            self.push_replace_line(self.markers.unescape_content_line(&line));
         } else if self.is_searching_active() {
-            self.current_search_content.push_str(&line);
-            self.current_search_content.push('\n');
+            self.push_search_line(self.markers.unescape_content_line(&line));
         } else if can_write_pending_non_standard_lines {
             // 处理非标内容
             self.pending_non_standard_lines.push(line);
@@ -381,66 +1002,231 @@ impl NewFileContentConstructor {
     }
 
     fn before_replace(&mut self) -> Result<(), DiffError> {
-        if self.current_search_content.is_empty() {
+        if self.parse_only {
+            return Ok(());
+        }
+
+        self.validate_modifiers()?;
+        let modifiers = &self.current_modifiers;
+        // The SEARCH content of a move is its source, located like a replacement
+        let kind = if modifiers.move_text {
+            BlockKind::Replace
+        } else {
+            modifiers.kind
+        };
+        let is_file_boundary = matches!(kind, BlockKind::Prepend | BlockKind::Append);
+        let mut scope_start = if modifiers.whole_file || is_file_boundary {
+            0
+        } else {
+            self.last_processed_index
+        };
+
+        if kind == BlockKind::Prepend {
+            self.current_matches.push(0..0);
+        } else if kind == BlockKind::Append {
+            let end = self.original_content.len();
+            self.current_matches.push(end..end);
+        } else if self.current_search_content.is_empty() {
             // Empty search block
             if self.original_content.is_empty() {
                 // New file scenario: nothing to match, just start inserting
-                self.search_match_index = 0;
-                self.search_end_index = 0;
+                self.current_matches.push(0..0);
             } else {
                 // Complete file replacement scenario: treat the entire file as matched
-                self.search_match_index = 0;
-                self.search_end_index = self.original_content.len() as isize;
+                self.limits
+                    .check_whole_file_rewrite(self.original_content)?;
+                self.current_matches.push(0..self.original_content.len());
+            }
+        } else if modifiers.occurrence == Occurrence::First
+            && modifiers.line_hint.is_none()
+            && !modifiers.whole_file
+            && !modifiers.regex
+        {
+            let found =
+                find_search_match(self.line_index(), &self.current_search_content, scope_start);
+            match found {
+                Some((match_start, match_end, strategy)) => {
+                    self.current_matches.push(match_start..match_end);
+                    self.current_strategy = Some(strategy);
+                }
+                // Blocks listed out of order are looked up in the text before the
+                // previous block, like the v1 engine does
+                None if scope_start > 0 => {
+                    let (matches, strategy) = self.find_candidates(0)?;
+                    let range = matches
+                        .into_iter()
+                        .next()
+                        .ok_or_else(|| self.not_found(&self.current_search_content))?;
+                    scope_start = 0;
+                    self.current_matches.push(range);
+                    self.current_strategy = strategy;
+                }
+                None => return Err(self.not_found(&self.current_search_content)),
             }
         } else {
-            // Exact search match scenario
-            if let Some(exact_index) = self.original_content[self.last_processed_index..]
-                .find(&self.current_search_content)
+            let (mut matches, mut strategy) = self.find_candidates(scope_start)?;
+            if matches.is_empty()
+                && scope_start > 0
+                && self.current_modifiers.occurrence == Occurrence::First
             {
-                let exact_index = self.last_processed_index + exact_index;
-                self.search_match_index = exact_index as isize;
-                self.search_end_index = (exact_index + self.current_search_content.len()) as isize;
-            } else {
-                // Attempt fallback line-trimmed matching
-                if let Some((match_start, match_end)) = line_trimmed_fallback_match(
-                    &self.original_content,
-                    &self.current_search_content,
-                    self.last_processed_index,
-                ) {
-                    self.search_match_index = match_start as isize;
-                    self.search_end_index = match_end as isize;
-                } else {
-                    // Try block anchor fallback for larger blocks
-                    if let Some((match_start, match_end)) = block_anchor_fallback_match(
-                        &self.original_content,
-                        &self.current_search_content,
-                        self.last_processed_index,
-                    ) {
-                        self.search_match_index = match_start as isize;
-                        self.search_end_index = match_end as isize;
-                    } else {
-                        return Err(DiffError::SearchBlockNotFound(
-                            self.current_search_content.trim_end().to_string(),
-                        ));
+                scope_start = 0;
+                (matches, strategy) = self.find_candidates(scope_start)?;
+            }
+            if matches.is_empty() {
+                return Err(self.not_found(&self.current_search_content));
+            }
+            let modifiers = &self.current_modifiers;
+            self.current_strategy = strategy;
+            self.current_matches = match (modifiers.occurrence, modifiers.line_hint) {
+                (Occurrence::All, _) => matches,
+                (Occurrence::Nth(n), _) => match n.checked_sub(1).and_then(|i| matches.get(i)) {
+                    Some(range) => vec![range.clone()],
+                    None => {
+                        return Err(DiffError::OccurrenceNotFound {
+                            search: self.current_search_content.trim_end().to_string(),
+                            occurrence: n,
+                            found: matches.len(),
+                        });
                     }
+                },
+                (Occurrence::First, Some(line_hint)) => {
+                    let closest = matches.into_iter().min_by_key(|range| {
+                        let line = self.line_index().line_containing(range.start) + 1;
+                        line.abs_diff(line_hint)
+                    });
+                    closest.into_iter().collect()
                 }
-            }
+                (Occurrence::First, None) => matches.into_iter().take(1).collect(),
+            };
         }
 
-        if (self.search_match_index as usize) < self.last_processed_index {
+        if let Some(max) = self.current_modifiers.max_replacements {
+            self.current_matches.truncate(max);
+        }
+
+        // An insertion point moved to a line boundary can land inside an earlier splice
+        let is_misplaced = |range: &Range<usize>| {
+            range.start < scope_start
+                || self.overlaps_splice(range)
+                || self.overlaps_splice(&self.edit_range(range))
+        };
+        if self.current_matches.iter().any(is_misplaced) {
+            self.current_matches.clear();
             return Err(DiffError::SearchBlockIncorrectMatch(
                 self.current_search_content.trim_end().to_string(),
             ));
         }
 
-        // Output everything up to the match location
-        self.result.push_str(
-            &self.original_content[self.last_processed_index..self.search_match_index as usize],
-        );
-
         Ok(())
     }
 
+    /// Finds the occurrences of the SEARCH content at or after `scope_start`, leaving out
+    /// text already replaced by earlier blocks
+    fn find_candidates(
+        &mut self,
+        scope_start: usize,
+    ) -> Result<(Vec<Range<usize>>, Option<MatchStrategy>), DiffError> {
+        let (matches, strategy) = if self.current_modifiers.regex {
+            let regex = match self.current_regex.take() {
+                Some(regex) => regex,
+                None => {
+                    let pattern = self
+                        .current_search_content
+                        .strip_suffix('\n')
+                        .unwrap_or(&self.current_search_content);
+                    RegexBuilder::new(pattern)
+                        .multi_line(self.current_modifiers.multiline)
+                        .build()
+                        .map_err(|err| DiffError::InvalidRegex(err.to_string()))?
+                }
+            };
+            // Searching from the scope start keeps a match beginning before it from
+            // hiding one inside it
+            let content = self.original_content;
+            let mut matches: Vec<(usize, usize)> = Vec::new();
+            let mut position = scope_start;
+            while position <= content.len()
+                && let Some(m) = regex.find_at(content, position)
+            {
+                // Like `find_iter`, an empty match right after the previous one is skipped
+                if !(m.is_empty() && matches.last().is_some_and(|&(_, end)| end == m.end())) {
+                    matches.push((m.start(), m.end()));
+                }
+                position = if m.is_empty() {
+                    m.end() + content[m.end()..].chars().next().map_or(1, char::len_utf8)
+                } else {
+                    m.end()
+                };
+            }
+            self.current_regex = Some(regex);
+            (matches, Some(MatchStrategy::Regex))
+        } else {
+            find_all_search_matches(self.line_index(), &self.current_search_content, scope_start)
+        };
+        // Text replaced by earlier blocks no longer contains the occurrence
+        let matches = matches
+            .into_iter()
+            .map(|(start, end)| start..end)
+            .filter(|range| !self.overlaps_splice(range))
+            .collect();
+        Ok((matches, strategy))
+    }
+
+    /// Rejects modifier combinations that don't fit the block's SEARCH content
+    fn validate_modifiers(&self) -> Result<(), DiffError> {
+        let modifiers = &self.current_modifiers;
+        let is_file_boundary = matches!(modifiers.kind, BlockKind::Prepend | BlockKind::Append);
+        let problem = if modifiers.occurrence == Occurrence::Nth(0)
+            || modifiers.max_replacements == Some(0)
+        {
+            // Only reachable through blocks built in code; the markers never parse to these
+            Some("#n and MAX=n need a number greater than zero")
+        } else if modifiers.move_text {
+            if self.current_search_content.is_empty() {
+                Some("MOVE blocks need the text to move in the SEARCH section")
+            } else if modifiers.kind == BlockKind::Replace {
+                Some("MOVE blocks need a destination: BEFORE, AFTER, PREPEND or APPEND")
+            } else if modifiers.regex || modifiers.occurrence == Occurrence::All {
+                Some("MOVE blocks can't be combined with REGEX or ALL")
+            } else {
+                None
+            }
+        } else if is_file_boundary && !self.current_search_content.is_empty() {
+            Some("PREPEND and APPEND blocks must have an empty SEARCH section")
+        } else if modifiers.kind.is_insertion()
+            && !is_file_boundary
+            && self.current_search_content.is_empty()
+        {
+            Some("BEFORE and AFTER blocks need an anchor in the SEARCH section")
+        } else {
+            None
+        };
+        match problem {
+            Some(problem) => Err(DiffError::InvalidBlockModifiers(problem.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// The error for SEARCH content, or a MOVE destination anchor, found nowhere in the file
+    fn not_found(&self, search: &str) -> DiffError {
+        let search = search.trim_end().to_string();
+        if self.current_modifiers.regex || self.line_index().allows_fuzzy_matching() {
+            DiffError::SearchBlockNotFound(search)
+        } else {
+            DiffError::ExactMatchRequired {
+                search,
+                max_line_length: self.max_fuzzy_line_length,
+            }
+        }
+    }
+
+    /// Returns true if the range overlaps text already replaced by a finished block
+    fn overlaps_splice(&self, range: &Range<usize>) -> bool {
+        self.splices
+            .iter()
+            .any(|s| s.range.start < range.end && range.start < s.range.end)
+    }
+
     fn try_fix_search_block(&mut self, line_limit: usize) -> Result<usize, DiffError> {
         let mut remove_line_count = 0;
         let line_limit = if line_limit == 0 {
@@ -449,15 +1235,12 @@ impl NewFileContentConstructor {
             line_limit
         };
 
-        let search_tag_regexp = Regex::new(r"^([-]{3,}|[<]{3,}) SEARCH$").unwrap();
         let search_tag_index = self
-            .find_last_matching_line_index(&search_tag_regexp, line_limit)
+            .find_last_matching_line_index(MarkerSet::is_search_block_start, line_limit)
             .ok_or(DiffError::InvalidReplaceMarker(0))?;
 
-        let fix_lines: Vec<String> =
-            self.pending_non_standard_lines[search_tag_index..line_limit].to_vec();
-        let mut fix_lines = fix_lines;
-        fix_lines[0] = SEARCH_BLOCK_START.to_string();
+        // The first line is already a search start marker; keep it for its modifiers
+        let fix_lines = self.pending_lines(search_tag_index..line_limit)?;
 
         for line in fix_lines {
             remove_line_count += self.internal_process_line(line, false, search_tag_index)?;
@@ -474,17 +1257,15 @@ impl NewFileContentConstructor {
             line_limit
         };
 
-        let replace_begin_tag_regexp = Regex::new(r"^[=]{3,}$").unwrap();
         let replace_begin_tag_index = self
-            .find_last_matching_line_index(&replace_begin_tag_regexp, line_limit)
+            .find_last_matching_line_index(MarkerSet::is_search_block_end, line_limit)
             .ok_or(DiffError::MalformedReplaceBlock(0))?;
 
-        let fix_lines: Vec<String> = self.pending_non_standard_lines[replace_begin_tag_index
-            .saturating_sub(remove_line_count)
-            ..line_limit.saturating_sub(remove_line_count)]
-            .to_vec();
-        let mut fix_lines = fix_lines;
-        fix_lines[0] = SEARCH_BLOCK_END.to_string();
+        let mut fix_lines = self.pending_lines(
+            replace_begin_tag_index.saturating_sub(remove_line_count)
+                ..line_limit.saturating_sub(remove_line_count),
+        )?;
+        fix_lines[0] = self.markers.search_block_end().to_string();
 
         for line in fix_lines {
             remove_line_count += self.internal_process_line(
@@ -505,19 +1286,18 @@ impl NewFileContentConstructor {
             line_limit
         };
 
-        let replace_end_tag_regexp = Regex::new(r"^([+]{3,}|[>]{3,}) REPLACE$").unwrap();
         let replace_end_tag_index =
-            self.find_last_matching_line_index(&replace_end_tag_regexp, line_limit);
-        let like_replace_end_tag = replace_end_tag_index == Some(line_limit - 1);
-
-        if like_replace_end_tag {
-            let replace_end_tag_index = replace_end_tag_index.unwrap();
-            let mut fix_lines: Vec<String> = self.pending_non_standard_lines[replace_end_tag_index
-                .saturating_sub(remove_line_count)
-                ..line_limit.saturating_sub(remove_line_count)]
-                .to_vec();
+            self.find_last_matching_line_index(MarkerSet::is_replace_block_end, line_limit);
+
+        if let Some(replace_end_tag_index) =
+            replace_end_tag_index.filter(|&index| index == line_limit - 1)
+        {
+            let mut fix_lines = self.pending_lines(
+                replace_end_tag_index.saturating_sub(remove_line_count)
+                    ..line_limit.saturating_sub(remove_line_count),
+            )?;
             let last_idx = fix_lines.len() - 1;
-            fix_lines[last_idx] = REPLACE_BLOCK_END.to_string();
+            fix_lines[last_idx] = self.markers.replace_block_end().to_string();
 
             for line in fix_lines {
                 remove_line_count += self.internal_process_line(
@@ -551,33 +1331,243 @@ impl NewFileContentConstructor {
     }
 }
 
+/// A single SEARCH/REPLACE edit, independent of the syntax it was written in.
+///
+/// Both contents are line-based: each line ends with `\n`, as in the diff body.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EditBlock {
+    pub search: String,
+    pub replace: String,
+    pub modifiers: BlockModifiers,
+}
+
+/// Splits block content into lines, ignoring the newline that terminates the last one
+fn content_lines(content: &str) -> impl Iterator<Item = &str> {
+    let lines =
+        (!content.is_empty()).then(|| content.strip_suffix('\n').unwrap_or(content).split('\n'));
+    lines.into_iter().flatten()
+}
+
+/// A replacement of a range of the original content
+#[derive(Debug, Clone)]
+pub(crate) struct Splice {
+    pub(crate) range: Range<usize>,
+    pub(crate) replacement: String,
+}
+
+impl Splice {
+    /// Sort key placing insertions before a replacement starting at the same position
+    pub(crate) fn key(&self) -> (usize, usize) {
+        (self.range.start, self.range.end)
+    }
+}
+
+/// What applying a single block changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockReport {
+    /// Byte ranges of the original content that were replaced, in file order
+    pub ranges: Vec<Range<usize>>,
+    /// How the SEARCH content was located; `None` for an empty SEARCH section
+    pub strategy: Option<MatchStrategy>,
+}
+
+impl BlockReport {
+    /// Number of replacements the block made
+    pub fn count(&self) -> usize {
+        self.ranges.len()
+    }
+}
+
+/// The new file content along with a report for each applied block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffReport {
+    pub content: String,
+    /// One entry per block that matched, in diff order
+    pub blocks: Vec<BlockReport>,
+}
+
+/// Options controlling how a diff is parsed and applied
+#[derive(Debug, Clone)]
+pub struct DiffOptions {
+    /// Marker vocabulary delimiting SEARCH/REPLACE blocks
+    pub markers: MarkerSet,
+    /// Apply identical blocks matching the same text once instead of reporting them as
+    /// overlapping (v1 engine)
+    pub merge_duplicate_blocks: bool,
+    /// Files with a line longer than this many bytes, such as minified bundles, are only
+    /// matched exactly: fuzzy matching is slow and unreliable on them
+    pub max_fuzzy_line_length: usize,
+    /// Bounds on the diff and the file, for diffs from untrusted sources
+    pub limits: Limits,
+    /// Flush files written by [`apply_to_path`] to disk before returning
+    pub sync_writes: bool,
+    /// Refuse to apply the diff unless the original content is still the one it was
+    /// written against
+    pub expected_original: Option<Precondition>,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            markers: MarkerSet::default(),
+            merge_duplicate_blocks: false,
+            max_fuzzy_line_length: DEFAULT_MAX_FUZZY_LINE_LENGTH,
+            limits: Limits::default(),
+            sync_writes: false,
+            expected_original: None,
+        }
+    }
+}
+
+impl DiffOptions {
+    pub(crate) fn check_original(&self, original_content: &str) -> Result<(), DiffError> {
+        match &self.expected_original {
+            Some(precondition) => precondition.check_content(original_content),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Applies a SEARCH/REPLACE diff to the original content.
+///
+/// Each block is resolved against the original content, preferably after the previous
+/// block; the replacements are then applied in file order, so blocks listed out of order
+/// still apply as long as they don't overlap. When `is_final` is false the result is a
+/// preview that ends with the content streamed so far.
 pub fn construct_new_file_content_v2(
     diff_content: &str,
     original_content: &str,
     is_final: bool,
 ) -> Result<String, DiffError> {
-    let mut constructor = NewFileContentConstructor::new(original_content.to_string(), is_final);
+    construct_new_file_content_v2_with_options(
+        diff_content,
+        original_content,
+        is_final,
+        &DiffOptions::default(),
+    )
+}
+
+/// Same as [`construct_new_file_content_v2`], with custom [`DiffOptions`]
+pub fn construct_new_file_content_v2_with_options(
+    diff_content: &str,
+    original_content: &str,
+    is_final: bool,
+    options: &DiffOptions,
+) -> Result<String, DiffError> {
+    construct_new_file_content_v2_with_report(diff_content, original_content, is_final, options)
+        .map(|report| report.content)
+}
+
+/// Same as [`construct_new_file_content_v2_with_options`], also reporting where each
+/// block matched and how many replacements it made
+pub fn construct_new_file_content_v2_with_report(
+    diff_content: &str,
+    original_content: &str,
+    is_final: bool,
+    options: &DiffOptions,
+) -> Result<DiffReport, DiffError> {
+    process_diff(diff_content, original_content, is_final, options)?.into_report()
+}
+
+/// The replacements each block of a diff makes in the original content, in diff order
+pub(crate) fn resolve_block_splices(
+    diff_content: &str,
+    original_content: &str,
+    options: &DiffOptions,
+) -> Result<Vec<Vec<Splice>>, DiffError> {
+    process_diff(diff_content, original_content, true, options)?.into_block_splices()
+}
+
+fn process_diff<'a>(
+    diff_content: &str,
+    original_content: &'a str,
+    is_final: bool,
+    options: &'a DiffOptions,
+) -> Result<NewFileContentConstructor<'a>, DiffError> {
+    options.check_original(original_content)?;
+    options.limits.check_diff_size(diff_content.len())?;
+    options.limits.check_file_size(original_content.len())?;
+    ensure_text_content(original_content)?;
+    let mut constructor = NewFileContentConstructor::new(original_content, is_final, options);
 
     let mut lines: Vec<&str> = diff_content.split('\n').collect();
 
     // If the last line looks like a partial marker but isn't recognized, remove it
-    if let Some(last_line) = lines.last()
-        && !last_line.is_empty()
-            && (last_line.starts_with(SEARCH_BLOCK_CHAR)
-                || last_line.starts_with(LEGACY_SEARCH_BLOCK_CHAR)
-                || last_line.starts_with("=")
-                || last_line.starts_with(REPLACE_BLOCK_CHAR)
-                || last_line.starts_with(LEGACY_REPLACE_BLOCK_CHAR))
-            && *last_line != SEARCH_BLOCK_START
-            && *last_line != SEARCH_BLOCK_END
-            && *last_line != REPLACE_BLOCK_END
-        {
-            lines.pop();
-        }
+    if lines
+        .last()
+        .is_some_and(|last_line| options.markers.is_partial_marker(last_line))
+    {
+        lines.pop();
+    }
 
     for line in lines {
         constructor.process_line(line.to_string())?;
     }
 
+    Ok(constructor)
+}
+
+/// Parses a SEARCH/REPLACE diff into its blocks, applying the same marker repairs as
+/// [`construct_new_file_content_v2`] but without matching them against any file
+pub fn parse_search_replace_blocks(
+    diff_content: &str,
+    options: &DiffOptions,
+) -> Result<Vec<EditBlock>, DiffError> {
+    let mut constructor = NewFileContentConstructor::new_parse_only(options);
+
+    for line in diff_content.split('\n') {
+        constructor.process_line(line.to_string())?;
+    }
+
+    constructor.into_blocks()
+}
+
+/// Applies already parsed blocks to the original content with the v2 matching engine
+pub fn apply_edit_blocks(
+    blocks: &[EditBlock],
+    original_content: &str,
+    is_final: bool,
+    options: &DiffOptions,
+) -> Result<String, DiffError> {
+    options.check_original(original_content)?;
+    options.limits.check_file_size(original_content.len())?;
+    ensure_text_content(original_content)?;
+    let mut constructor = NewFileContentConstructor::new(original_content, is_final, options);
+
+    for block in blocks {
+        constructor.process_block(block)?;
+    }
+
     constructor.get_result()
 }
+
+/// Renders blocks as a diff in the given marker vocabulary, escaping content lines that
+/// would otherwise be read as markers
+pub fn format_search_replace_blocks(blocks: &[EditBlock], markers: &MarkerSet) -> String {
+    let mut diff = String::new();
+    for block in blocks {
+        diff.push_str(markers.search_block_start());
+        if !block.modifiers.is_empty() {
+            diff.push(' ');
+            diff.push_str(&block.modifiers.to_string());
+        }
+        diff.push('\n');
+        for line in content_lines(&block.search) {
+            diff.push_str(&markers.escape_content_line(line));
+            diff.push('\n');
+        }
+        diff.push_str(markers.search_block_end());
+        diff.push('\n');
+        if let Some(replace_start) = markers.replace_block_start() {
+            diff.push_str(replace_start);
+            diff.push('\n');
+        }
+        for line in content_lines(&block.replace) {
+            diff.push_str(&markers.escape_content_line(line));
+            diff.push('\n');
+        }
+        diff.push_str(markers.replace_block_end());
+        diff.push('\n');
+    }
+    diff
+}
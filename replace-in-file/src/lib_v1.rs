@@ -1,7 +1,11 @@
 use regex::Regex;
+use std::cell::OnceCell;
 use std::sync::OnceLock;
 
-use crate::DiffError;
+use crate::{
+    DiffError, DiffOptions, Limits, LineIndex, block_anchor_fallback_match, ensure_text_content,
+    line_trimmed_fallback_match,
+};
 
 const SEARCH_BLOCK_CHAR: &str = "-";
 const REPLACE_BLOCK_CHAR: &str = "+";
@@ -45,117 +49,51 @@ fn is_replace_block_end(line: &str) -> bool {
     replace_block_end_regex().is_match(line) || legacy_replace_block_end_regex().is_match(line)
 }
 
-fn line_trimmed_fallback_match(
-    original_content: &str,
+/// Checks a finished block against the limits, `finished` blocks having come before it
+fn check_block_limits(
+    limits: &Limits,
+    finished: usize,
     search_content: &str,
-    start_index: usize,
-) -> Option<(usize, usize)> {
-    let original_lines: Vec<&str> = original_content.split('\n').collect();
-    let mut search_lines: Vec<&str> = search_content.split('\n').collect();
-
-    if search_lines.last().is_some_and(|l| l.is_empty()) {
-        search_lines.pop();
-    }
-
-    if search_lines.is_empty() {
-        return None;
-    }
-
-    let mut start_line_num = 0;
-    let mut current_index = 0;
-    while current_index < start_index && start_line_num < original_lines.len() {
-        current_index += original_lines[start_line_num].len() + 1;
-        start_line_num += 1;
-    }
-
-    for i in start_line_num..=original_lines.len().saturating_sub(search_lines.len()) {
-        let mut matches = true;
-        for (j, search_line) in search_lines.iter().enumerate() {
-            let original_trimmed = original_lines[i + j].trim();
-            let search_trimmed = search_line.trim();
-            if original_trimmed != search_trimmed {
-                matches = false;
-                break;
-            }
-        }
-        if matches {
-            let mut match_start_index = 0;
-            for k in 0..i {
-                match_start_index += original_lines[k].len() + 1;
-            }
-            let mut match_end_index = match_start_index;
-            for k in 0..search_lines.len() {
-                match_end_index += original_lines[i + k].len();
-                // add newline offset only if not the last line of the file
-                if (i + k) < original_lines.len() - 1 {
-                    match_end_index += 1;
-                }
-            }
-            return Some((match_start_index, match_end_index));
-        }
-    }
-
-    None
+    matched: &str,
+    replace_content: &str,
+    original_content: &str,
+) -> Result<(), DiffError> {
+    limits.check_block_count(finished)?;
+    limits.check_deletion(
+        search_content,
+        [(matched, replace_content)],
+        original_content.len(),
+    )
 }
 
-fn block_anchor_fallback_match(
+pub fn construct_new_file_content_v1(
+    diff_content: &str,
     original_content: &str,
-    search_content: &str,
-    start_index: usize,
-) -> Option<(usize, usize)> {
-    let original_lines: Vec<&str> = original_content.split('\n').collect();
-    let mut search_lines: Vec<&str> = search_content.split('\n').collect();
-
-    if search_lines.len() < 3 {
-        return None;
-    }
-
-    if search_lines.last().is_some_and(|l| l.is_empty()) {
-        search_lines.pop();
-    }
-
-    let first_line_search = search_lines[0].trim();
-    let last_line_search = search_lines[search_lines.len() - 1].trim();
-    let search_block_size = search_lines.len();
-
-    let mut start_line_num = 0;
-    let mut current_index = 0;
-    while current_index < start_index && start_line_num < original_lines.len() {
-        current_index += original_lines[start_line_num].len() + 1;
-        start_line_num += 1;
-    }
-
-    for i in start_line_num..=original_lines.len().saturating_sub(search_block_size) {
-        if original_lines[i].trim() != first_line_search {
-            continue;
-        }
-        if original_lines[i + search_block_size - 1].trim() != last_line_search {
-            continue;
-        }
-
-        let mut match_start_index = 0;
-        for k in 0..i {
-            match_start_index += original_lines[k].len() + 1;
-        }
-        let mut match_end_index = match_start_index;
-        for k in 0..search_block_size {
-            match_end_index += original_lines[i + k].len();
-            // add newline offset only if not the last line of the file
-            if (i + k) < original_lines.len() - 1 {
-                match_end_index += 1;
-            }
-        }
-        return Some((match_start_index, match_end_index));
-    }
-
-    None
+    is_final: bool,
+) -> Result<String, DiffError> {
+    construct_new_file_content_v1_with_options(
+        diff_content,
+        original_content,
+        is_final,
+        &DiffOptions::default(),
+    )
 }
 
-pub fn construct_new_file_content_v1(
+/// Same as [`construct_new_file_content_v1`], with custom [`DiffOptions`].
+///
+/// The v1 engine honors `merge_duplicate_blocks`, `max_fuzzy_line_length`, `limits` and
+/// `expected_original`; its markers are fixed.
+pub fn construct_new_file_content_v1_with_options(
     diff_content: &str,
     original_content: &str,
     is_final: bool,
+    options: &DiffOptions,
 ) -> Result<String, DiffError> {
+    options.check_original(original_content)?;
+    let limits = &options.limits;
+    limits.check_diff_size(diff_content.len())?;
+    limits.check_file_size(original_content.len())?;
+    ensure_text_content(original_content)?;
     let mut result = String::new();
     let mut last_processed_index: usize = 0;
 
@@ -167,23 +105,27 @@ pub fn construct_new_file_content_v1(
     let mut search_match_index: isize = -1;
     let mut search_end_index: isize = -1;
 
-    let mut replacements: Vec<(usize, usize, String)> = Vec::new();
+    // (start, end, content, block index)
+    let mut replacements: Vec<(usize, usize, String, usize)> = Vec::new();
     let mut pending_out_of_order_replacement = false;
 
+    let line_index = OnceCell::new();
+    let line_index = || {
+        line_index.get_or_init(|| LineIndex::new(original_content, options.max_fuzzy_line_length))
+    };
+
     let mut lines: Vec<&str> = diff_content.split('\n').collect();
-    if let Some(last_line) = lines.last().copied() {
-        if !lines.is_empty()
-            && (last_line.starts_with(SEARCH_BLOCK_CHAR)
-                || last_line.starts_with(LEGACY_SEARCH_BLOCK_CHAR)
-                || last_line.starts_with('=')
-                || last_line.starts_with(REPLACE_BLOCK_CHAR)
-                || last_line.starts_with(LEGACY_REPLACE_BLOCK_CHAR))
-            && !is_search_block_start(last_line)
-            && !is_search_block_end(last_line)
-            && !is_replace_block_end(last_line)
-        {
-            lines.pop();
-        }
+    if let Some(last_line) = lines.last().copied()
+        && (last_line.starts_with(SEARCH_BLOCK_CHAR)
+            || last_line.starts_with(LEGACY_SEARCH_BLOCK_CHAR)
+            || last_line.starts_with('=')
+            || last_line.starts_with(REPLACE_BLOCK_CHAR)
+            || last_line.starts_with(LEGACY_REPLACE_BLOCK_CHAR))
+        && !is_search_block_start(last_line)
+        && !is_search_block_end(last_line)
+        && !is_replace_block_end(last_line)
+    {
+        lines.pop();
     }
 
     for line in lines {
@@ -216,6 +158,7 @@ pub fn construct_new_file_content_v1(
 
             if current_search_content.is_empty() {
                 // TS v1 lenient behavior: empty SEARCH means replace entire file
+                limits.check_whole_file_rewrite(original_content)?;
                 search_match_index = 0;
                 search_end_index = original_content.len() as isize;
             } else {
@@ -226,24 +169,34 @@ pub fn construct_new_file_content_v1(
                     let exact = last_processed_index + exact_index;
                     search_match_index = exact as isize;
                     search_end_index = (exact + current_search_content.len()) as isize;
-                } else if let Some((start, end)) =
-                    line_trimmed_fallback_match(original_content, &current_search_content, last_processed_index)
-                {
+                } else if let Some((start, end)) = line_trimmed_fallback_match(
+                    line_index(),
+                    &current_search_content,
+                    last_processed_index,
+                ) {
                     search_match_index = start as isize;
                     search_end_index = end as isize;
-                } else if let Some((start, end)) =
-                    block_anchor_fallback_match(original_content, &current_search_content, last_processed_index)
-                {
+                } else if let Some((start, end)) = block_anchor_fallback_match(
+                    line_index(),
+                    &current_search_content,
+                    last_processed_index,
+                ) {
                     search_match_index = start as isize;
                     search_end_index = end as isize;
-                } else if let Some(full_file_index) = original_content.find(&current_search_content) {
+                } else if let Some(full_file_index) = original_content.find(&current_search_content)
+                {
                     search_match_index = full_file_index as isize;
                     search_end_index = (full_file_index + current_search_content.len()) as isize;
                     if (search_match_index as usize) < last_processed_index {
                         pending_out_of_order_replacement = true;
                     }
-                } else {
+                } else if line_index().allows_fuzzy_matching() {
                     return Err(DiffError::SearchBlockNotFound(current_search_content.trim_end().to_string()));
+                } else {
+                    return Err(DiffError::ExactMatchRequired {
+                        search: current_search_content.trim_end().to_string(),
+                        max_line_length: options.max_fuzzy_line_length,
+                    });
                 }
             }
 
@@ -261,11 +214,20 @@ pub fn construct_new_file_content_v1(
             if search_match_index == -1 {
                 return Err(DiffError::NoLinesAvailable);
             }
+            check_block_limits(
+                limits,
+                replacements.len(),
+                &current_search_content,
+                &original_content[search_match_index as usize..search_end_index as usize],
+                &current_replace_content,
+                original_content,
+            )?;
 
             replacements.push((
                 search_match_index as usize,
                 search_end_index as usize,
                 current_replace_content.clone(),
+                replacements.len(),
             ));
 
             if !pending_out_of_order_replacement {
@@ -297,28 +259,48 @@ pub fn construct_new_file_content_v1(
 
     if is_final {
         if in_replace && search_match_index != -1 {
+            check_block_limits(
+                limits,
+                replacements.len(),
+                &current_search_content,
+                &original_content[search_match_index as usize..search_end_index as usize],
+                &current_replace_content,
+                original_content,
+            )?;
+            let block_index = replacements.len();
             replacements.push((
                 search_match_index as usize,
                 search_end_index as usize,
-                current_replace_content.clone(),
+                current_replace_content,
+                block_index,
             ));
-            if !pending_out_of_order_replacement {
-                last_processed_index = search_end_index as usize;
-            }
-            in_search = false;
-            in_replace = false;
-            current_search_content.clear();
-            current_replace_content.clear();
-            search_match_index = -1;
-            search_end_index = -1;
-            pending_out_of_order_replacement = false;
         }
 
-        replacements.sort_by_key(|(start, _, _)| *start);
+        replacements.sort_by_key(|(start, end, _, index)| (*start, *end, *index));
+        if options.merge_duplicate_blocks {
+            replacements
+                .dedup_by(|next, prev| next.0 == prev.0 && next.1 == prev.1 && next.2 == prev.2);
+        }
+
+        // Each replacement must start after the ones before it have ended
+        let mut covered: Option<(usize, usize)> = None;
+        for (start, end, _, index) in replacements.iter() {
+            if let Some((covered_end, covering_index)) = covered
+                && *start < covered_end
+            {
+                return Err(DiffError::OverlappingBlocks {
+                    first: covering_index.min(*index),
+                    second: covering_index.max(*index),
+                });
+            }
+            if covered.is_none_or(|(covered_end, _)| *end >= covered_end) {
+                covered = Some((*end, *index));
+            }
+        }
 
         result.clear();
         let mut current_pos = 0usize;
-        for (start, end, content) in replacements.iter() {
+        for (start, end, content, _) in replacements.iter() {
             result.push_str(&original_content[current_pos..*start]);
             result.push_str(content);
             current_pos = *end;
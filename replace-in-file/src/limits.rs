@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use crate::DiffError;
+
+/// Bounds on diffs from untrusted sources and on the files they edit.
+///
+/// Every limit is off by default; each one that is exceeded fails the diff with its own
+/// error before anything is written.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Limits {
+    /// Largest diff accepted, in bytes
+    pub max_diff_bytes: Option<usize>,
+    /// Most SEARCH/REPLACE blocks accepted in one diff
+    pub max_blocks: Option<usize>,
+    /// Largest file that can be edited, in bytes
+    pub max_file_bytes: Option<usize>,
+    /// Largest share of the file, from 0.0 to 1.0, a single block may remove: the bytes
+    /// of the matched lines that its REPLACE content doesn't keep
+    pub max_deleted_fraction: Option<f64>,
+    /// Refuse empty SEARCH blocks, which otherwise replace the whole content of a
+    /// non-empty file
+    pub forbid_whole_file_rewrite: bool,
+}
+
+impl Limits {
+    pub(crate) fn check_diff_size(&self, size: usize) -> Result<(), DiffError> {
+        match self.max_diff_bytes {
+            Some(limit) if size > limit => Err(DiffError::DiffTooLarge { size, limit }),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_file_size(&self, size: usize) -> Result<(), DiffError> {
+        match self.max_file_bytes {
+            Some(limit) if size > limit => Err(DiffError::FileTooLarge { size, limit }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks there is room for another block after `finished` ones
+    pub(crate) fn check_block_count(&self, finished: usize) -> Result<(), DiffError> {
+        match self.max_blocks {
+            Some(limit) if finished >= limit => Err(DiffError::TooManyBlocks { limit }),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_whole_file_rewrite(&self, original_content: &str) -> Result<(), DiffError> {
+        if self.forbid_whole_file_rewrite && !original_content.is_empty() {
+            return Err(DiffError::WholeFileRewriteForbidden);
+        }
+        Ok(())
+    }
+
+    /// Checks a block making `edits`, each replacing matched text of a `file_size` file
+    pub(crate) fn check_deletion<'a>(
+        &self,
+        search: &str,
+        edits: impl IntoIterator<Item = (&'a str, &'a str)>,
+        file_size: usize,
+    ) -> Result<(), DiffError> {
+        let Some(fraction) = self.max_deleted_fraction else {
+            return Ok(());
+        };
+        let deleted: usize = edits
+            .into_iter()
+            .map(|(matched, replacement)| removed_bytes(matched, replacement))
+            .sum();
+        if deleted > 0 && deleted as f64 > fraction * file_size as f64 {
+            return Err(DiffError::BlockDeletesTooMuch {
+                search: search.trim_end().to_string(),
+                deleted,
+                file_size,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The bytes of the lines of `matched` that `replacement` doesn't have, so that rewriting
+/// text counts as removing it while lines kept around an insertion don't
+fn removed_bytes(matched: &str, replacement: &str) -> usize {
+    let mut kept: HashMap<&str, usize> = HashMap::new();
+    for line in replacement.lines() {
+        *kept.entry(line).or_default() += 1;
+    }
+    matched
+        .split_inclusive('\n')
+        .filter(
+            |line| match kept.get_mut(line.trim_end_matches(['\r', '\n'])) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            },
+        )
+        .map(str::len)
+        .sum()
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+/// Line boundaries of a file and a lookup from trimmed line content to line numbers.
+///
+/// Built once per file and shared by every fuzzy match against it, so matching a block
+/// costs time proportional to the candidate lines rather than to the whole file.
+/// Lines are separated by `\n` and numbered from 0.
+///
+/// Files with overly long lines get no lookup, which leaves every fuzzy matcher without
+/// candidates: only exact matches are possible.
+pub(crate) struct LineIndex<'a> {
+    content: &'a str,
+    /// Byte offset where each line starts
+    starts: Vec<usize>,
+    /// Line numbers of each distinct trimmed line, ascending
+    by_trimmed: HashMap<&'a str, Vec<usize>>,
+    allows_fuzzy_matching: bool,
+}
+
+impl<'a> LineIndex<'a> {
+    /// Indexes `content`, allowing fuzzy matching only if no line is longer than
+    /// `max_fuzzy_line_length` bytes
+    pub(crate) fn new(content: &'a str, max_fuzzy_line_length: usize) -> Self {
+        let mut starts = vec![0];
+        starts.extend(content.match_indices('\n').map(|(i, _)| i + 1));
+
+        let longest_line = starts
+            .windows(2)
+            .map(|pair| pair[1] - pair[0] - 1)
+            .chain(starts.last().map(|&last| content.len() - last))
+            .max()
+            .unwrap_or(0);
+        let allows_fuzzy_matching = longest_line <= max_fuzzy_line_length;
+
+        let mut by_trimmed: HashMap<&str, Vec<usize>> = HashMap::new();
+        if allows_fuzzy_matching {
+            for (number, line) in content.split('\n').enumerate() {
+                by_trimmed.entry(line.trim()).or_default().push(number);
+            }
+        }
+
+        Self {
+            content,
+            starts,
+            by_trimmed,
+            allows_fuzzy_matching,
+        }
+    }
+
+    pub(crate) fn allows_fuzzy_matching(&self) -> bool {
+        self.allows_fuzzy_matching
+    }
+
+    pub(crate) fn content(&self) -> &'a str {
+        self.content
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// The line without its `\n`
+    pub(crate) fn line(&self, number: usize) -> &'a str {
+        let end = self
+            .starts
+            .get(number + 1)
+            .map_or(self.content.len(), |next| next - 1);
+        &self.content[self.starts[number]..end]
+    }
+
+    /// Byte offset where the line starts, or the end of the content past the last line
+    pub(crate) fn start_of(&self, number: usize) -> usize {
+        self.starts
+            .get(number)
+            .copied()
+            .unwrap_or(self.content.len())
+    }
+
+    /// The first line starting at or after the byte at `index`
+    pub(crate) fn first_line_from(&self, index: usize) -> usize {
+        self.starts.partition_point(|&start| start < index)
+    }
+
+    /// 0-based number of the line containing the byte at `index`
+    pub(crate) fn line_containing(&self, index: usize) -> usize {
+        self.starts.partition_point(|&start| start <= index) - 1
+    }
+
+    /// Ascending numbers of the lines whose trimmed content is `trimmed`
+    pub(crate) fn lines_trimmed_to(&self, trimmed: &str) -> &[usize] {
+        self.by_trimmed.get(trimmed).map_or(&[], Vec::as_slice)
+    }
+
+    /// Byte range covering `count` lines from `first`, including the line break after the
+    /// last one unless it ends the file
+    pub(crate) fn byte_range(&self, first: usize, count: usize) -> (usize, usize) {
+        (self.start_of(first), self.start_of(first + count))
+    }
+}
@@ -0,0 +1,381 @@
+use regex::Regex;
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::OnceLock;
+
+pub(crate) const SEARCH_BLOCK_START: &str = "------- SEARCH";
+pub(crate) const SEARCH_BLOCK_END: &str = "=======";
+pub(crate) const REPLACE_BLOCK_END: &str = "+++++++ REPLACE";
+
+const SEARCH_BLOCK_CHAR: char = '-';
+const REPLACE_BLOCK_CHAR: char = '+';
+const LEGACY_SEARCH_BLOCK_CHAR: char = '<';
+const LEGACY_REPLACE_BLOCK_CHAR: char = '>';
+const SEARCH_BLOCK_END_CHAR: char = '=';
+
+const MARKER_ESCAPE_CHAR: char = '\\';
+
+/// Whitespace-separated modifier tokens allowed after a SEARCH start marker
+const MODIFIERS_PATTERN: &str = r"(?P<modifiers>(?:\s+\S+)*)";
+
+/// Which occurrences of the SEARCH content a block replaces
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Occurrence {
+    /// The first occurrence, or the one closest to the line hint
+    #[default]
+    First,
+    /// The n-th occurrence, 1-based (`#<n>`)
+    Nth(usize),
+    /// Every occurrence (`ALL`)
+    All,
+}
+
+/// What a block does with the text it matches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BlockKind {
+    /// Replace the matched text with the REPLACE content
+    #[default]
+    Replace,
+    /// Insert the REPLACE content on the lines before the matched anchor (`BEFORE`)
+    InsertBefore,
+    /// Insert the REPLACE content on the lines after the matched anchor (`AFTER`)
+    InsertAfter,
+    /// Insert the REPLACE content at the start of the file; SEARCH must be empty (`PREPEND`)
+    Prepend,
+    /// Insert the REPLACE content at the end of the file; SEARCH must be empty (`APPEND`)
+    Append,
+}
+
+/// Per-block options written after the SEARCH start marker, e.g. `------- SEARCH @L120`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockModifiers {
+    pub kind: BlockKind,
+    /// 1-based line the SEARCH content is expected at (`@L<n>`). When the content occurs
+    /// more than once, the occurrence closest to this line is used.
+    pub line_hint: Option<usize>,
+    pub occurrence: Occurrence,
+    /// Look for occurrences in the whole file (`FILE`) instead of only after the previous
+    /// block. Occurrences inside text replaced by earlier blocks are not counted.
+    pub whole_file: bool,
+    /// The SEARCH content is a regular expression and the REPLACE content may refer to its
+    /// capture groups as `$1` or `${name}` (`REGEX`). The final newline of both sections is
+    /// not part of the pattern or replacement.
+    pub regex: bool,
+    /// Let `^` and `$` match at line boundaries in a regular expression (`MULTILINE`)
+    pub multiline: bool,
+    /// Replace at most this many occurrences (`MAX=<n>`)
+    pub max_replacements: Option<usize>,
+    /// Move the text matched by the SEARCH content to the destination given by the kind
+    /// (`MOVE`). For `BEFORE` and `AFTER` the REPLACE content is the destination anchor;
+    /// for `PREPEND` and `APPEND` it must be empty.
+    pub move_text: bool,
+}
+
+impl BlockModifiers {
+    /// Parses the tokens following a SEARCH start marker; returns `None` if any token
+    /// isn't a known modifier, so that the line is not taken for a marker
+    fn parse(tokens: &str) -> Option<Self> {
+        let mut modifiers = Self::default();
+        for token in tokens.split_whitespace() {
+            if let Some(kind) = BlockKind::from_token(token) {
+                // A block has a single kind
+                if modifiers.kind != BlockKind::Replace {
+                    return None;
+                }
+                modifiers.kind = kind;
+            } else if let Some(line) = token.strip_prefix("@L") {
+                modifiers.line_hint = Some(line.parse().ok()?);
+            } else if let Some(n) = token.strip_prefix('#') {
+                modifiers.occurrence = Occurrence::Nth(n.parse().ok().filter(|&n| n > 0)?);
+            } else if token == "ALL" {
+                modifiers.occurrence = Occurrence::All;
+            } else if token == "FILE" {
+                modifiers.whole_file = true;
+            } else if token == "MOVE" {
+                modifiers.move_text = true;
+            } else if token == "REGEX" {
+                modifiers.regex = true;
+            } else if token == "MULTILINE" {
+                modifiers.multiline = true;
+            } else if let Some(max) = token.strip_prefix("MAX=") {
+                modifiers.max_replacements = Some(max.parse().ok().filter(|&max| max > 0)?);
+            } else {
+                return None;
+            }
+        }
+        Some(modifiers)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl BlockKind {
+    const TOKENS: [(BlockKind, &'static str); 4] = [
+        (BlockKind::InsertBefore, "BEFORE"),
+        (BlockKind::InsertAfter, "AFTER"),
+        (BlockKind::Prepend, "PREPEND"),
+        (BlockKind::Append, "APPEND"),
+    ];
+
+    fn from_token(token: &str) -> Option<Self> {
+        Self::TOKENS
+            .iter()
+            .find(|(_, name)| *name == token)
+            .map(|(kind, _)| *kind)
+    }
+
+    fn token(self) -> Option<&'static str> {
+        Self::TOKENS
+            .iter()
+            .find(|(kind, _)| *kind == self)
+            .map(|(_, name)| *name)
+    }
+
+    /// True for kinds that insert content instead of replacing the match
+    pub fn is_insertion(self) -> bool {
+        self != BlockKind::Replace
+    }
+}
+
+impl fmt::Display for BlockModifiers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut tokens = Vec::new();
+        if self.move_text {
+            tokens.push("MOVE".to_string());
+        }
+        if let Some(kind) = self.kind.token() {
+            tokens.push(kind.to_string());
+        }
+        if let Some(line) = self.line_hint {
+            tokens.push(format!("@L{line}"));
+        }
+        match self.occurrence {
+            Occurrence::First => {}
+            Occurrence::Nth(n) => tokens.push(format!("#{n}")),
+            Occurrence::All => tokens.push("ALL".to_string()),
+        }
+        if self.whole_file {
+            tokens.push("FILE".to_string());
+        }
+        if self.regex {
+            tokens.push("REGEX".to_string());
+        }
+        if self.multiline {
+            tokens.push("MULTILINE".to_string());
+        }
+        if let Some(max) = self.max_replacements {
+            tokens.push(format!("MAX={max}"));
+        }
+        write!(f, "{}", tokens.join(" "))
+    }
+}
+
+/// The delimiter vocabulary of a SEARCH/REPLACE diff.
+///
+/// The default set is the `------- SEARCH` / `=======` / `+++++++ REPLACE` format (plus the
+/// legacy `<<<<<<< SEARCH` / `>>>>>>> REPLACE` variants). Tools that prompt with other tags
+/// can build their own set and share the same matching and repair logic.
+#[derive(Debug, Clone)]
+pub struct MarkerSet {
+    search_start: Vec<Regex>,
+    search_end: Vec<Regex>,
+    replace_start: Vec<Regex>,
+    replace_end: Vec<Regex>,
+    canonical_search_start: String,
+    canonical_search_end: String,
+    canonical_replace_start: Option<String>,
+    canonical_replace_end: String,
+    /// Leading characters that make an unrecognized trailing line a partial marker
+    marker_chars: Vec<char>,
+}
+
+impl Default for MarkerSet {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl MarkerSet {
+    /// The standard `------- SEARCH` / `=======` / `+++++++ REPLACE` vocabulary
+    pub fn standard() -> Self {
+        static STANDARD: OnceLock<MarkerSet> = OnceLock::new();
+        STANDARD
+            .get_or_init(|| Self {
+                search_start: vec![
+                    Regex::new(&format!(r"^[-]{{3,}} SEARCH>?{MODIFIERS_PATTERN}$")).unwrap(),
+                    Regex::new(&format!(r"^[<]{{3,}} SEARCH>?{MODIFIERS_PATTERN}$")).unwrap(),
+                ],
+                search_end: vec![Regex::new(r"^[=]{3,}$").unwrap()],
+                replace_start: Vec::new(),
+                replace_end: vec![
+                    Regex::new(r"^[+]{3,} REPLACE>?$").unwrap(),
+                    Regex::new(r"^[>]{3,} REPLACE>?$").unwrap(),
+                ],
+                canonical_search_start: SEARCH_BLOCK_START.to_string(),
+                canonical_search_end: SEARCH_BLOCK_END.to_string(),
+                canonical_replace_start: None,
+                canonical_replace_end: REPLACE_BLOCK_END.to_string(),
+                marker_chars: vec![
+                    SEARCH_BLOCK_CHAR,
+                    LEGACY_SEARCH_BLOCK_CHAR,
+                    SEARCH_BLOCK_END_CHAR,
+                    REPLACE_BLOCK_CHAR,
+                    LEGACY_REPLACE_BLOCK_CHAR,
+                ],
+            })
+            .clone()
+    }
+
+    /// Builds a vocabulary from literal marker lines, e.g. `<search>`, `</search>`, `</replace>`.
+    ///
+    /// Each marker must occupy a whole line; surrounding whitespace is ignored. The search
+    /// start marker may be followed by [`BlockModifiers`], e.g. `<search> @L12`.
+    pub fn new(search_start: &str, search_end: &str, replace_end: &str) -> Self {
+        Self {
+            search_start: vec![
+                Regex::new(&format!(
+                    r"^\s*{}{MODIFIERS_PATTERN}\s*$",
+                    regex::escape(search_start.trim())
+                ))
+                .unwrap(),
+            ],
+            search_end: vec![literal_line_regex(search_end)],
+            replace_start: Vec::new(),
+            replace_end: vec![literal_line_regex(replace_end)],
+            canonical_search_start: search_start.trim().to_string(),
+            canonical_search_end: search_end.trim().to_string(),
+            canonical_replace_start: None,
+            canonical_replace_end: replace_end.trim().to_string(),
+            marker_chars: Vec::new(),
+        }
+    }
+
+    /// Adds a marker that opens the REPLACE section after the SEARCH section is closed.
+    ///
+    /// Blank lines between the two are ignored; if the marker is missing the REPLACE
+    /// content simply starts right after the SEARCH section.
+    pub fn with_replace_start(mut self, replace_start: &str) -> Self {
+        self.replace_start = vec![literal_line_regex(replace_start)];
+        self.canonical_replace_start = Some(replace_start.trim().to_string());
+        self
+    }
+
+    /// XML-like tags, e.g. `xml_tags("search", "replace")` for
+    /// `<search>`, `</search>`, `<replace>` and `</replace>` lines
+    pub fn xml_tags(search_tag: &str, replace_tag: &str) -> Self {
+        Self::new(
+            &format!("<{search_tag}>"),
+            &format!("</{search_tag}>"),
+            &format!("</{replace_tag}>"),
+        )
+        .with_replace_start(&format!("<{replace_tag}>"))
+    }
+
+    pub(crate) fn is_search_block_start(&self, line: &str) -> bool {
+        self.parse_search_block_start(line).is_some()
+    }
+
+    /// Returns the modifiers of a SEARCH start marker line, or `None` if it isn't one
+    pub(crate) fn parse_search_block_start(&self, line: &str) -> Option<BlockModifiers> {
+        self.search_start.iter().find_map(|r| {
+            let captures = r.captures(line)?;
+            BlockModifiers::parse(captures.name("modifiers").map_or("", |m| m.as_str()))
+        })
+    }
+
+    pub(crate) fn is_search_block_end(&self, line: &str) -> bool {
+        self.search_end.iter().any(|r| r.is_match(line))
+    }
+
+    pub(crate) fn has_replace_block_start(&self) -> bool {
+        !self.replace_start.is_empty()
+    }
+
+    pub(crate) fn is_replace_block_start(&self, line: &str) -> bool {
+        self.replace_start.iter().any(|r| r.is_match(line))
+    }
+
+    pub(crate) fn is_replace_block_end(&self, line: &str) -> bool {
+        self.replace_end.iter().any(|r| r.is_match(line))
+    }
+
+    pub(crate) fn search_block_start(&self) -> &str {
+        &self.canonical_search_start
+    }
+
+    pub(crate) fn search_block_end(&self) -> &str {
+        &self.canonical_search_end
+    }
+
+    pub(crate) fn replace_block_start(&self) -> Option<&str> {
+        self.canonical_replace_start.as_deref()
+    }
+
+    pub(crate) fn replace_block_end(&self) -> &str {
+        &self.canonical_replace_end
+    }
+
+    fn is_marker(&self, line: &str) -> bool {
+        self.is_search_block_start(line)
+            || self.is_search_block_end(line)
+            || self.is_replace_block_start(line)
+            || self.is_replace_block_end(line)
+    }
+
+    /// Returns true if a trailing diff line looks like a marker that is still being streamed
+    pub(crate) fn is_partial_marker(&self, line: &str) -> bool {
+        if line.is_empty() {
+            return false;
+        }
+        let canonical = [
+            self.canonical_search_start.as_str(),
+            self.canonical_search_end.as_str(),
+            self.canonical_replace_end.as_str(),
+        ];
+        let starts_with_marker_char = line
+            .chars()
+            .next()
+            .is_some_and(|c| self.marker_chars.contains(&c));
+
+        (starts_with_marker_char && !canonical.contains(&line))
+            || canonical
+                .iter()
+                .any(|marker| marker.len() > line.len() && marker.starts_with(line))
+    }
+
+    /// Returns true if the line is a marker, or a marker escaped one or more times
+    fn is_marker_or_escaped_marker(&self, line: &str) -> bool {
+        self.is_marker(line)
+            || line
+                .strip_prefix(MARKER_ESCAPE_CHAR)
+                .is_some_and(|rest| self.is_marker_or_escaped_marker(rest))
+    }
+
+    /// Escapes a content line that would otherwise be read as a marker, the inverse of
+    /// [`MarkerSet::unescape_content_line`]
+    pub(crate) fn escape_content_line<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        if self.is_marker_or_escaped_marker(line) {
+            Cow::Owned(format!("{MARKER_ESCAPE_CHAR}{line}"))
+        } else {
+            Cow::Borrowed(line)
+        }
+    }
+
+    /// Removes one level of escaping from a SEARCH/REPLACE content line.
+    ///
+    /// Content lines that would otherwise be read as markers (e.g. `=======` in a file with
+    /// merge-conflict examples) are written with a leading backslash (`\=======`). Any other
+    /// line, including ones that merely start with a backslash, is returned unchanged.
+    pub(crate) fn unescape_content_line<'a>(&self, line: &'a str) -> &'a str {
+        match line.strip_prefix(MARKER_ESCAPE_CHAR) {
+            Some(rest) if self.is_marker_or_escaped_marker(rest) => rest,
+            _ => line,
+        }
+    }
+}
+
+fn literal_line_regex(marker: &str) -> Regex {
+    Regex::new(&format!(r"^\s*{}\s*$", regex::escape(marker.trim()))).unwrap()
+}
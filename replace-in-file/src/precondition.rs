@@ -0,0 +1,88 @@
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::DiffError;
+
+/// What the original content must still be for a diff to apply, so edits planned against
+/// an earlier read of a file aren't applied to content changed since
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Precondition {
+    /// SHA-256 of the original content as text, in hexadecimal, as given by
+    /// [`content_sha256`]
+    Sha256(String),
+    /// Modification time and size in bytes of the file when it was read. Only file-level
+    /// functions such as [`apply_to_path`](crate::apply_to_path) can check it; the others
+    /// ignore it.
+    Modified { mtime: SystemTime, size: u64 },
+}
+
+impl Precondition {
+    /// The precondition holding for the file `metadata` describes
+    pub fn modified_of(metadata: &fs::Metadata) -> std::io::Result<Self> {
+        Ok(Self::Modified {
+            mtime: metadata.modified()?,
+            size: metadata.len(),
+        })
+    }
+
+    pub(crate) fn check_content(&self, content: &str) -> Result<(), DiffError> {
+        match self {
+            Self::Sha256(expected) => {
+                let actual = content_sha256(content);
+                if !expected.trim().eq_ignore_ascii_case(&actual) {
+                    return Err(DiffError::StaleOriginal {
+                        expected: self.to_string(),
+                        actual: Self::Sha256(actual).to_string(),
+                    });
+                }
+                Ok(())
+            }
+            Self::Modified { .. } => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_file(&self, metadata: &fs::Metadata) -> Result<(), DiffError> {
+        match self {
+            Self::Modified { .. } => {
+                let actual = Self::modified_of(metadata)?;
+                if actual != *self {
+                    return Err(DiffError::StaleOriginal {
+                        expected: self.to_string(),
+                        actual: actual.to_string(),
+                    });
+                }
+                Ok(())
+            }
+            Self::Sha256(_) => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Precondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sha256(hash) => write!(f, "sha256 {}", hash.trim().to_ascii_lowercase()),
+            Self::Modified { mtime, size } => {
+                let since_epoch = mtime.duration_since(UNIX_EPOCH).unwrap_or_default();
+                write!(
+                    f,
+                    "modified at {}.{:09}s, {size} bytes",
+                    since_epoch.as_secs(),
+                    since_epoch.subsec_nanos()
+                )
+            }
+        }
+    }
+}
+
+/// SHA-256 of text content in lowercase hexadecimal, for [`Precondition::Sha256`].
+///
+/// Files are hashed as decoded text, so the hash doesn't depend on their encoding.
+pub fn content_sha256(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
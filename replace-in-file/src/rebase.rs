@@ -0,0 +1,255 @@
+use std::ops::Range;
+
+use crate::{
+    DiffError, DiffOptions, LineIndex, MatchStrategy, Splice, ensure_text_content,
+    find_search_match, resolve_block_splices,
+};
+
+/// A replacement of a rebased diff that couldn't be applied to the current content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RebaseConflict {
+    /// Index of the block in the diff
+    pub block: usize,
+    /// The text the block replaced in the content the diff was written against
+    pub base: String,
+    /// What the block replaced it with
+    pub replacement: String,
+    /// Range of the current content that stands where the base text was, if the lines
+    /// around it could be located
+    pub current_range: Option<Range<usize>>,
+    /// The current text in `current_range`
+    pub current: String,
+}
+
+/// The result of [`rebase_diff`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RebaseOutcome {
+    /// The current content with every replacement that applied cleanly; conflicting
+    /// regions are left as they currently are
+    pub content: String,
+    /// Replacements that didn't apply, in the order of the content the diff was written
+    /// against
+    pub conflicts: Vec<RebaseConflict>,
+}
+
+impl RebaseOutcome {
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// Applies a diff written against `base` to `current`, a later version of the same file.
+///
+/// The diff is resolved against `base` first, which must succeed. Each replacement is then
+/// merged into `current`: the base text it replaced is located there, exactly or up to
+/// whitespace around lines, and replaced likewise, and insertions are placed after the
+/// text they followed. Text that isn't unique is located together with the lines before
+/// it. A replacement or insertion whose base text was changed in `current`, or is
+/// ambiguous there, is a conflict, unless `current` already has the change there.
+pub fn rebase_diff(
+    diff_content: &str,
+    base: &str,
+    current: &str,
+    options: &DiffOptions,
+) -> Result<RebaseOutcome, DiffError> {
+    ensure_text_content(current)?;
+    let blocks = resolve_block_splices(diff_content, base, options)?;
+    let base_lines = LineIndex::new(base, options.max_fuzzy_line_length);
+    let current_lines = LineIndex::new(current, options.max_fuzzy_line_length);
+
+    let mut hunks: Vec<(usize, Splice)> = blocks
+        .into_iter()
+        .enumerate()
+        .flat_map(|(block, splices)| splices.into_iter().map(move |splice| (block, splice)))
+        .collect();
+    hunks.sort_by_key(|(_, hunk)| hunk.key());
+
+    let mut edits: Vec<Splice> = Vec::new();
+    let mut conflicts = Vec::new();
+    // Hunks are looked up after the previous one, like blocks are
+    let mut anchor = Anchor::default();
+    for (block, hunk) in hunks {
+        let located = locate(&base_lines, &current_lines, &hunk.range, &anchor);
+
+        match located {
+            // Both sides inserted the same text
+            Some(range)
+                if range.is_empty()
+                    && !hunk.replacement.is_empty()
+                    && current[range.start..].starts_with(&hunk.replacement)
+                    && !base[hunk.range.start..].starts_with(&hunk.replacement) =>
+            {
+                anchor = Anchor {
+                    base: hunk.range.start,
+                    current: range.start + hunk.replacement.len(),
+                };
+            }
+            Some(range) if !edits.iter().any(|edit| overlaps(&edit.range, &range)) => {
+                anchor = Anchor {
+                    base: hunk.range.end,
+                    current: range.end,
+                };
+                edits.push(Splice {
+                    range,
+                    replacement: hunk.replacement,
+                });
+            }
+            _ => {
+                let region = surrounded_region(&base_lines, &current_lines, &hunk.range, &anchor);
+                let current_text = region.as_ref().map_or("", |r| &current[r.clone()]);
+                if let Some(region) = &region {
+                    anchor = Anchor {
+                        base: hunk.range.end,
+                        current: region.end,
+                    };
+                    // Both sides made the same replacement
+                    if !hunk.range.is_empty() && current_text == hunk.replacement {
+                        continue;
+                    }
+                }
+                conflicts.push(RebaseConflict {
+                    block,
+                    base: base[hunk.range].to_string(),
+                    replacement: hunk.replacement,
+                    current: current_text.to_string(),
+                    current_range: region,
+                });
+            }
+        }
+    }
+
+    edits.sort_by_key(Splice::key);
+    let mut content = String::with_capacity(current.len());
+    let mut position = 0;
+    for edit in &edits {
+        content.push_str(&current[position..edit.range.start]);
+        content.push_str(&edit.replacement);
+        position = edit.range.end;
+    }
+    content.push_str(&current[position..]);
+
+    Ok(RebaseOutcome { content, conflicts })
+}
+
+/// Positions of `base` and `current` known to correspond, where the previous hunk ended
+#[derive(Debug, Default)]
+struct Anchor {
+    base: usize,
+    current: usize,
+}
+
+/// The outcome of looking up text from a position on
+enum Lookup {
+    Missing,
+    Ambiguous,
+    Found(Range<usize>, MatchStrategy),
+}
+
+/// Looks up text that must occur exactly once at or after `start`. Only exact and
+/// line-trimmed matches count, since the looser fallbacks would let a replacement
+/// overwrite concurrent edits.
+fn find_unique(lines: &LineIndex, text: &str, start: usize) -> Lookup {
+    let find = |start| {
+        find_search_match(lines, text, start)
+            .filter(|(_, _, strategy)| {
+                matches!(strategy, MatchStrategy::Exact | MatchStrategy::LineTrimmed)
+            })
+            .map(|(start, end, strategy)| (start..end, strategy))
+    };
+    let Some((first, strategy)) = find(start) else {
+        return Lookup::Missing;
+    };
+    let next = lines.content()[first.start..]
+        .chars()
+        .next()
+        .map_or(first.start + 1, |c| first.start + c.len_utf8());
+    if text.is_empty() || find(next).is_some() {
+        return Lookup::Ambiguous;
+    }
+    Lookup::Found(first, strategy)
+}
+
+/// Locates the base `range` in `current`, or the point an insertion at it goes to.
+///
+/// The base text is widened by the lines before it until it is unique in `base` after
+/// the anchor, or reaches the anchor, in which case it must follow the anchor directly in
+/// `current` too. Otherwise it must be unique in `current` after the anchor, or, if it
+/// moved before it, in all of `current`.
+fn locate(
+    base: &LineIndex,
+    current: &LineIndex,
+    range: &Range<usize>,
+    anchor: &Anchor,
+) -> Option<Range<usize>> {
+    if range.start < anchor.base {
+        return None;
+    }
+    let needle_end = if range.is_empty() {
+        range.start
+    } else {
+        range.end
+    };
+    let mut from = range.start;
+    while from > anchor.base
+        && !matches!(
+            find_unique(base, &base.content()[from..needle_end], anchor.base),
+            Lookup::Found(ref found, _) if found.start == from
+        )
+    {
+        from = base
+            .start_of(base.line_containing(from - 1))
+            .max(anchor.base);
+    }
+    let needle = &base.content()[from..needle_end];
+
+    let (found, strategy) = if from == anchor.base {
+        find_search_match(current, needle, anchor.current)
+            .filter(|&(start, _, strategy)| {
+                start == anchor.current
+                    && matches!(strategy, MatchStrategy::Exact | MatchStrategy::LineTrimmed)
+            })
+            .map(|(start, end, strategy)| (start..end, strategy))?
+    } else {
+        match find_unique(current, needle, anchor.current) {
+            Lookup::Found(found, strategy) => (found, strategy),
+            Lookup::Missing => match find_unique(current, needle, 0) {
+                Lookup::Found(found, strategy) => (found, strategy),
+                _ => return None,
+            },
+            Lookup::Ambiguous => return None,
+        }
+    };
+    if range.is_empty() {
+        Some(found.end..found.end)
+    } else if strategy == MatchStrategy::Exact {
+        Some(found.end - range.len()..found.end)
+    } else {
+        // Where the range starts within fuzzily matched context is unknown
+        (from == range.start).then_some(found)
+    }
+}
+
+/// The range of `current` between the text found before and the line after `range` in
+/// `base`
+fn surrounded_region(
+    base: &LineIndex,
+    current: &LineIndex,
+    range: &Range<usize>,
+    anchor: &Anchor,
+) -> Option<Range<usize>> {
+    let start = locate(base, current, &(range.start..range.start), anchor)?.start;
+    let end = if range.end >= base.content().len() {
+        current.content().len()
+    } else {
+        let line = base.line_containing(range.end);
+        let after = &base.content()[range.end..base.start_of(line + 1)];
+        find_search_match(current, after, start).map(|(start, _, _)| start)?
+    };
+    (start <= end).then_some(start..end)
+}
+
+/// Whether an edit of `range` would conflict with one of `other`, insertions only
+/// conflicting with replacements around them
+fn overlaps(other: &Range<usize>, range: &Range<usize>) -> bool {
+    range.start < other.end && other.start < range.end
+}
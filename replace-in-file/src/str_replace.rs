@@ -0,0 +1,108 @@
+use serde::Deserialize;
+
+use crate::{
+    DiffError, DiffOptions, LineIndex, MatchStrategy, ensure_text_content, find_all_search_matches,
+};
+
+/// Arguments of a `str_replace` editor call, as sent by agent frameworks:
+/// `{"path": ..., "old_str": ..., "new_str": ..., "replace_all": ...}`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct StrReplaceRequest {
+    pub path: String,
+    pub old_str: String,
+    #[serde(default)]
+    pub new_str: String,
+    #[serde(default)]
+    pub replace_all: bool,
+}
+
+/// The result of a successful `str_replace`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrReplaceOutcome {
+    pub content: String,
+    /// 1-based line numbers in the original content where replacements start
+    pub lines: Vec<usize>,
+    pub strategy: MatchStrategy,
+}
+
+/// Replaces `old_str` with `new_str` in the content of `request.path`.
+///
+/// `old_str` is located with the v2 matching pipeline (exact, then line-trimmed, block
+/// anchor and elided fallbacks), and must match exactly once unless `replace_all` is set.
+pub fn str_replace(
+    content: &str,
+    request: &StrReplaceRequest,
+) -> Result<StrReplaceOutcome, DiffError> {
+    str_replace_with_options(content, request, &DiffOptions::default())
+}
+
+/// Same as [`str_replace`], with custom [`DiffOptions`]. `old_str` and `new_str` together
+/// count as the diff for the size limit, and the request as a single block.
+pub fn str_replace_with_options(
+    content: &str,
+    request: &StrReplaceRequest,
+    options: &DiffOptions,
+) -> Result<StrReplaceOutcome, DiffError> {
+    if request.old_str.is_empty() {
+        return Err(DiffError::EmptyOldStr);
+    }
+    if request.old_str == request.new_str {
+        return Err(DiffError::OldStrEqualsNewStr);
+    }
+
+    options.check_original(content)?;
+    let limits = &options.limits;
+    limits.check_diff_size(request.old_str.len() + request.new_str.len())?;
+    limits.check_file_size(content.len())?;
+    ensure_text_content(content)?;
+    let line_index = LineIndex::new(content, options.max_fuzzy_line_length);
+    let (matches, strategy) = find_all_search_matches(&line_index, &request.old_str, 0);
+    let Some(strategy) = strategy else {
+        return Err(DiffError::OldStrNotFound {
+            path: request.path.clone(),
+            old_str: request.old_str.clone(),
+        });
+    };
+
+    let lines: Vec<usize> = matches
+        .iter()
+        .map(|&(start, _)| line_index.line_containing(start) + 1)
+        .collect();
+    if matches.len() > 1 && !request.replace_all {
+        return Err(DiffError::OldStrNotUnique {
+            path: request.path.clone(),
+            old_str: request.old_str.clone(),
+            lines,
+        });
+    }
+    limits.check_deletion(
+        &request.old_str,
+        matches
+            .iter()
+            .map(|&(start, end)| (&content[start..end], request.new_str.as_str())),
+        content.len(),
+    )?;
+
+    let mut result = String::with_capacity(content.len());
+    let mut last_end = 0;
+    for &(start, end) in &matches {
+        result.push_str(&content[last_end..start]);
+        result.push_str(&request.new_str);
+        // Line-based fallbacks match whole lines, so keep the line break they consumed
+        if strategy != MatchStrategy::Exact
+            && !request.new_str.is_empty()
+            && !request.new_str.ends_with('\n')
+            && content[start..end].ends_with('\n')
+        {
+            result.push('\n');
+        }
+        last_end = end;
+    }
+    result.push_str(&content[last_end..]);
+
+    Ok(StrReplaceOutcome {
+        content: result,
+        lines,
+        strategy,
+    })
+}
@@ -0,0 +1,190 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::atomic_write::symlink_target;
+use crate::{
+    ApplyOutcome, DiffError, DiffOptions, WriteToFileOutcome, apply_to_path, write_to_file,
+};
+
+/// Paths denied by [`Workspace::new`]
+pub const DEFAULT_DENIED_PATHS: &[&str] = &[".git/", ".env", ".env.*"];
+
+/// A directory that file paths coming from an agent are confined to.
+///
+/// Paths are resolved against the root, following symlinks, and refused if they end up
+/// outside of it or match a denied pattern. Patterns are relative to the root and may
+/// use `*` within a name; a pattern without a `/` other than a trailing one matches that
+/// name at any depth, and a denied directory denies everything below it.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    /// Canonical root directory
+    root: PathBuf,
+    denied: Vec<String>,
+}
+
+impl Workspace {
+    /// A workspace rooted at an existing directory, denying [`DEFAULT_DENIED_PATHS`]
+    pub fn new(root: impl AsRef<Path>) -> Result<Self, DiffError> {
+        let root = root.as_ref();
+        let root = match fs::canonicalize(root) {
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Err(DiffError::PathNotFound(root.to_path_buf()));
+            }
+            result => result?,
+        };
+        Ok(Self {
+            root,
+            denied: DEFAULT_DENIED_PATHS.iter().map(|p| p.to_string()).collect(),
+        })
+    }
+
+    /// Replaces the denied patterns
+    pub fn with_denied_paths(mut self, patterns: &[&str]) -> Self {
+        self.denied = patterns.iter().map(|p| p.to_string()).collect();
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Resolves a path relative to the root, or an absolute path inside it, to a path
+    /// inside the root with every existing symlink followed.
+    ///
+    /// The path itself doesn't need to exist, so files can be created.
+    pub fn resolve(&self, path: impl AsRef<Path>) -> Result<PathBuf, DiffError> {
+        let requested = path.as_ref();
+        let outside = || DiffError::PathOutsideWorkspace(requested.to_path_buf());
+        let relative = if requested.is_absolute() {
+            requested
+                .strip_prefix(&self.root)
+                .map_err(|_| outside())?
+                .to_path_buf()
+        } else {
+            requested.to_path_buf()
+        };
+
+        let mut resolved = self.root.clone();
+        let mut hops = 0;
+        for component in relative.components() {
+            match component {
+                Component::CurDir => continue,
+                Component::ParentDir => {
+                    resolved.pop();
+                }
+                Component::Normal(name) => {
+                    resolved.push(name);
+                    if let Some(target) = follow_symlink(&resolved, &mut hops)? {
+                        if !target.starts_with(&self.root) {
+                            return Err(DiffError::SymlinkOutsideWorkspace {
+                                path: requested.to_path_buf(),
+                                target,
+                            });
+                        }
+                        resolved = target;
+                    }
+                }
+                // Only reachable for absolute paths outside the root
+                Component::RootDir | Component::Prefix(_) => return Err(outside()),
+            }
+            if !resolved.starts_with(&self.root) {
+                return Err(outside());
+            }
+        }
+
+        let inside = resolved.strip_prefix(&self.root).unwrap_or(Path::new(""));
+        if let Some(pattern) = self.denied.iter().find(|p| is_denied(p, inside)) {
+            return Err(DiffError::DeniedPath {
+                path: requested.to_path_buf(),
+                pattern: pattern.clone(),
+            });
+        }
+        Ok(resolved)
+    }
+
+    /// [`apply_to_path`] for a path resolved with [`Workspace::resolve`]
+    pub fn apply_to_path(
+        &self,
+        path: impl AsRef<Path>,
+        diff_content: &str,
+        options: &DiffOptions,
+    ) -> Result<ApplyOutcome, DiffError> {
+        apply_to_path(&self.resolve(path)?, diff_content, options)
+    }
+
+    /// [`write_to_file()`] for a path resolved with [`Workspace::resolve`]
+    pub fn write_to_file(
+        &self,
+        path: impl AsRef<Path>,
+        content: &str,
+        force: bool,
+    ) -> Result<WriteToFileOutcome, DiffError> {
+        write_to_file(&self.resolve(path)?, content, force)
+    }
+}
+
+/// Where the symlink at `path` leads, or `None` if it isn't one.
+///
+/// The target is resolved like a path of its own, following symlinks in every component,
+/// so a dangling link or a chain of them is judged by where writing through it would
+/// create the file.
+fn follow_symlink(path: &Path, hops: &mut usize) -> Result<Option<PathBuf>, DiffError> {
+    match symlink_target(path, hops)? {
+        Some(target) => real_path(&target, hops).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Resolves an absolute path with every symlink in it followed, whether or not the file
+/// at the end exists
+fn real_path(path: &Path, hops: &mut usize) -> Result<PathBuf, DiffError> {
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::Normal(name) => {
+                resolved.push(name);
+                if let Some(target) = follow_symlink(&resolved, hops)? {
+                    resolved = target;
+                }
+            }
+            component => resolved.push(component),
+        }
+    }
+    Ok(resolved)
+}
+
+fn is_denied(pattern: &str, path: &Path) -> bool {
+    let pattern = pattern.trim_end_matches('/');
+    let names: Vec<String> = path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    if !pattern.contains('/') {
+        return names.iter().any(|name| glob_match(pattern, name));
+    }
+    let parts: Vec<&str> = pattern.split('/').collect();
+    names.len() >= parts.len()
+        && parts
+            .iter()
+            .zip(&names)
+            .all(|(part, name)| glob_match(part, name))
+}
+
+/// Matches a name against a pattern where `*` stands for any run of characters
+fn glob_match(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => {
+            let Some(name) = name.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=name.len())
+                .filter(|&i| name.is_char_boundary(i))
+                .any(|i| glob_match(rest, &name[i..]))
+        }
+    }
+}
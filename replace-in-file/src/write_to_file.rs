@@ -0,0 +1,162 @@
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::atomic_write::write_atomically;
+use crate::{
+    DiffError, EditBlock, MarkerSet, decode_text, encode_text, format_search_replace_blocks,
+    is_elision_marker,
+};
+
+/// Unchanged lines kept around the changed region of a SEARCH/REPLACE equivalent
+const CONTEXT_LINES: usize = 3;
+
+/// The result of a successful `write_to_file`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteToFileOutcome {
+    /// True if the file didn't exist before
+    pub created: bool,
+    /// A SEARCH/REPLACE diff turning the previous content into the new one, for logging
+    pub diff: String,
+}
+
+fn elision_comment_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r"(?i)^\s*(?://|#|/\*|\*|<!--|--|;|%)\s*(?:\.{3}|…)?\s*(?:rest of (?:the )?(?:code|file|implementation|function|class|method)|(?:existing|previous|unchanged|remaining|other) (?:code|content|methods|functions|implementation)|(?:remains?|stays?) (?:the same|unchanged)|code omitted|omitted for brevity|same as (?:before|above))(?:\s+(?:goes here|here|unchanged|omitted|as before|(?:is|are|stays|remains) (?:the same|unchanged)))?\s*(?:\.{3}|…)?\s*(?:\*/|-->)?\s*$",
+        )
+        .unwrap()
+    })
+}
+
+fn bare_ellipsis_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"^\s*(?:\.{3}|…)\s*$").unwrap())
+}
+
+/// Returns a description of why the content looks accidentally truncated, if it does.
+///
+/// Content is suspicious when it stops in the middle of a line (unless the previous
+/// content didn't end with a newline either) or contains comments standing in for
+/// omitted code. A bare `...` line is allowed, since it is valid code in some languages.
+pub fn detect_truncation(content: &str, previous_content: Option<&str>) -> Option<String> {
+    for (i, line) in content.lines().enumerate() {
+        let elided = elision_comment_regex().is_match(line)
+            || (is_elision_marker(line) && !bare_ellipsis_regex().is_match(line));
+        if elided {
+            return Some(format!(
+                "line {} looks like a placeholder for omitted content: `{}`",
+                i + 1,
+                line.trim()
+            ));
+        }
+    }
+
+    let previous_ends_mid_line =
+        previous_content.is_some_and(|p| !p.is_empty() && !p.ends_with('\n'));
+    if !content.is_empty() && !content.ends_with('\n') && !previous_ends_mid_line {
+        return Some("the content ends in the middle of a line".to_string());
+    }
+
+    None
+}
+
+/// Writes the complete content of a file, creating missing parent directories.
+///
+/// Content that [`detect_truncation`] flags is refused unless `force` is set. An existing
+/// file is rewritten in the encoding it had, atomically and keeping its permissions; new
+/// files are UTF-8.
+pub fn write_to_file(
+    path: &Path,
+    content: &str,
+    force: bool,
+) -> Result<WriteToFileOutcome, DiffError> {
+    let previous = match fs::read(path) {
+        Ok(previous) => Some(decode_text(&previous)?),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err.into()),
+    };
+    let (previous, encoding) = previous.unzip();
+
+    if !force && let Some(reason) = detect_truncation(content, previous.as_deref()) {
+        return Err(DiffError::SuspectedTruncation {
+            path: path.to_path_buf(),
+            reason,
+        });
+    }
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    // An existing file keeps its encoding
+    write_atomically(
+        path,
+        &encode_text(content, encoding.unwrap_or_default())?,
+        false,
+    )?;
+
+    Ok(WriteToFileOutcome {
+        created: previous.is_none(),
+        diff: search_replace_equivalent(previous.as_deref().unwrap_or(""), content),
+    })
+}
+
+/// Computes a single SEARCH/REPLACE block that turns `original` into `updated`.
+///
+/// The block covers the changed lines plus a few lines of context, extended until the
+/// SEARCH content first occurs at the changed region. Identical contents give an empty
+/// diff. Since blocks are line-based, a missing final newline is not represented.
+pub fn search_replace_equivalent(original: &str, updated: &str) -> String {
+    if original == updated {
+        return String::new();
+    }
+
+    let original_lines: Vec<&str> = original.split_inclusive('\n').collect();
+    let updated_lines: Vec<&str> = updated.split_inclusive('\n').collect();
+
+    let common_prefix = original_lines
+        .iter()
+        .zip(&updated_lines)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let common_suffix = original_lines[common_prefix..]
+        .iter()
+        .rev()
+        .zip(updated_lines[common_prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut before = common_prefix.min(CONTEXT_LINES);
+    let mut after = common_suffix.min(CONTEXT_LINES);
+    loop {
+        let start = common_prefix - before;
+        let original_end = original_lines.len() - common_suffix + after;
+        let updated_end = updated_lines.len() - common_suffix + after;
+        let search = original_lines[start..original_end].concat();
+        let replace = updated_lines[start..updated_end].concat();
+
+        let start_offset: usize = original_lines[..start].iter().map(|l| l.len()).sum();
+        let is_first_occurrence = search.is_empty() || original.find(&search) == Some(start_offset);
+        let can_grow = before < common_prefix || after < common_suffix;
+        if is_first_occurrence || !can_grow {
+            let block = EditBlock {
+                search: with_final_newline(search),
+                replace: with_final_newline(replace),
+                ..Default::default()
+            };
+            return format_search_replace_blocks(&[block], &MarkerSet::standard());
+        }
+
+        before = (before + 1).min(common_prefix);
+        after = (after + 1).min(common_suffix);
+    }
+}
+
+fn with_final_newline(mut content: String) -> String {
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content
+}
@@ -0,0 +1,279 @@
+use crate::{DiffError, DiffOptions, EditBlock, apply_edit_blocks};
+
+const EDIT_TAG: &str = "edit";
+const OLD_TAG: &str = "old";
+const NEW_TAG: &str = "new";
+
+const CDATA_START: &str = "<![CDATA[";
+const CDATA_END: &str = "]]>";
+
+/// Parses XML-style edits such as `<edit><old>...</old><new>...</new></edit>` into blocks.
+///
+/// Text outside `<edit>` elements is ignored. Element content may use CDATA sections and
+/// the predefined and numeric character entities; an `&` that doesn't start a known
+/// entity is kept as is, since models rarely escape code. Content is line-based like a
+/// SEARCH/REPLACE block: a newline right after the opening tag and indentation before
+/// the closing tag are dropped.
+pub fn parse_xml_edit_blocks(input: &str) -> Result<Vec<EditBlock>, DiffError> {
+    parse_edits(input, false)
+}
+
+/// Applies XML-style edits to the original content with the v2 matching engine.
+///
+/// When `is_final` is false, a trailing `<edit>` that hasn't been closed yet is ignored.
+pub fn construct_new_file_content_xml(
+    xml_content: &str,
+    original_content: &str,
+    is_final: bool,
+) -> Result<String, DiffError> {
+    construct_new_file_content_xml_with_options(
+        xml_content,
+        original_content,
+        is_final,
+        &DiffOptions::default(),
+    )
+}
+
+/// Same as [`construct_new_file_content_xml`], with custom [`DiffOptions`]. The markers
+/// are not used; the limit on the number of blocks applies to `<edit>` elements.
+pub fn construct_new_file_content_xml_with_options(
+    xml_content: &str,
+    original_content: &str,
+    is_final: bool,
+    options: &DiffOptions,
+) -> Result<String, DiffError> {
+    options.limits.check_diff_size(xml_content.len())?;
+    let blocks = parse_edits(xml_content, !is_final)?;
+    if let Some(limit) = options.limits.max_blocks
+        && blocks.len() > limit
+    {
+        return Err(DiffError::TooManyBlocks { limit });
+    }
+    apply_edit_blocks(&blocks, original_content, is_final, options)
+}
+
+fn parse_edits(input: &str, allow_incomplete: bool) -> Result<Vec<EditBlock>, DiffError> {
+    let mut blocks = Vec::new();
+    let mut rest = input;
+
+    while let Some(start) = find_open_tag(rest, EDIT_TAG) {
+        let (self_closing, after_open) = split_open_tag(&rest[start..]);
+        if self_closing {
+            rest = after_open;
+            continue;
+        }
+        match parse_edit_body(after_open) {
+            Ok((block, after_edit)) => {
+                blocks.push(block);
+                rest = after_edit;
+            }
+            Err(EditParseError::Incomplete) if allow_incomplete => break,
+            Err(EditParseError::Incomplete) => {
+                return Err(DiffError::MalformedXmlEdit(format!(
+                    "unterminated <{EDIT_TAG}> element"
+                )));
+            }
+            Err(EditParseError::Malformed(message)) => {
+                return Err(DiffError::MalformedXmlEdit(message));
+            }
+        }
+    }
+
+    Ok(blocks)
+}
+
+enum EditParseError {
+    /// The input ended before the element was closed
+    Incomplete,
+    Malformed(String),
+}
+
+/// Parses the children of an `<edit>` element, returning the block and the remaining input
+fn parse_edit_body(input: &str) -> Result<(EditBlock, &str), EditParseError> {
+    let mut old = None;
+    let mut new = None;
+    let mut rest = input;
+
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return Err(EditParseError::Incomplete);
+        }
+        if let Some(after) = rest.strip_prefix(&format!("</{EDIT_TAG}>")) {
+            rest = after;
+            break;
+        }
+
+        let tag = [OLD_TAG, NEW_TAG]
+            .into_iter()
+            .find(|tag| find_open_tag(rest, tag) == Some(0));
+        let Some(tag) = tag else {
+            let streamed_prefix = [
+                format!("<{OLD_TAG}"),
+                format!("<{NEW_TAG}"),
+                format!("</{EDIT_TAG}>"),
+            ]
+            .iter()
+            .any(|expected| expected.starts_with(rest));
+            if streamed_prefix {
+                return Err(EditParseError::Incomplete);
+            }
+            return Err(EditParseError::Malformed(format!(
+                "expected <{OLD_TAG}>, <{NEW_TAG}> or </{EDIT_TAG}> inside <{EDIT_TAG}>"
+            )));
+        };
+
+        let (self_closing, after_open) = split_open_tag(rest);
+        let (content, after_close) = if self_closing {
+            (String::new(), after_open)
+        } else {
+            read_element_content(after_open, tag)?
+        };
+
+        let slot = if tag == OLD_TAG { &mut old } else { &mut new };
+        if slot.is_some() {
+            return Err(EditParseError::Malformed(format!(
+                "duplicate <{tag}> inside <{EDIT_TAG}>"
+            )));
+        }
+        *slot = Some(normalize_content(&content));
+        rest = after_close;
+    }
+
+    match (old, new) {
+        (Some(search), Some(replace)) => Ok((
+            EditBlock {
+                search,
+                replace,
+                ..Default::default()
+            },
+            rest,
+        )),
+        (None, _) => Err(EditParseError::Malformed(format!(
+            "missing <{OLD_TAG}> inside <{EDIT_TAG}>"
+        ))),
+        (_, None) => Err(EditParseError::Malformed(format!(
+            "missing <{NEW_TAG}> inside <{EDIT_TAG}>"
+        ))),
+    }
+}
+
+/// Finds `<tag>`, `<tag/>` or `<tag attr=...>`, but not `<tagname>`
+fn find_open_tag(input: &str, tag: &str) -> Option<usize> {
+    let needle = format!("<{tag}");
+    let mut offset = 0;
+    while let Some(found) = input[offset..].find(&needle) {
+        let start = offset + found;
+        let after = &input[start + needle.len()..];
+        match after.chars().next() {
+            Some(c) if c == '>' || c == '/' || c.is_whitespace() => return Some(start),
+            // A tag still being streamed
+            None => return None,
+            Some(_) => offset = start + needle.len(),
+        }
+    }
+    None
+}
+
+/// Splits off an opening tag starting at the beginning of `input`, reporting whether it
+/// was self-closing. An unterminated tag consumes the whole input.
+fn split_open_tag(input: &str) -> (bool, &str) {
+    match input.find('>') {
+        Some(end) => (input[..end].ends_with('/'), &input[end + 1..]),
+        None => (false, ""),
+    }
+}
+
+/// Reads decoded element content up to `</tag>`, returning it and the input after the tag
+fn read_element_content<'a>(
+    input: &'a str,
+    tag: &str,
+) -> Result<(String, &'a str), EditParseError> {
+    let close_tag = format!("</{tag}>");
+    let mut content = String::new();
+    let mut rest = input;
+
+    loop {
+        if let Some(after) = rest.strip_prefix(&close_tag) {
+            return Ok((content, after));
+        }
+        if let Some(after) = rest.strip_prefix(CDATA_START) {
+            let end = after.find(CDATA_END).ok_or(EditParseError::Incomplete)?;
+            content.push_str(&after[..end]);
+            rest = &after[end + CDATA_END.len()..];
+            continue;
+        }
+        if rest.starts_with('&') {
+            let (decoded, consumed) = decode_entity(rest);
+            content.push_str(&decoded);
+            rest = &rest[consumed..];
+            continue;
+        }
+
+        let mut chars = rest.chars();
+        match chars.next() {
+            Some(c) => {
+                content.push(c);
+                rest = chars.as_str();
+            }
+            None => return Err(EditParseError::Incomplete),
+        }
+    }
+}
+
+/// Decodes the character entity at the start of `input`, returning the text and the
+/// number of bytes consumed; unknown entities decode to a literal `&`
+fn decode_entity(input: &str) -> (String, usize) {
+    let literal = ("&".to_string(), 1);
+    let Some(end) = input[1..].find(';').map(|i| i + 1) else {
+        return literal;
+    };
+    let name = &input[1..end];
+
+    let decoded = match name {
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "amp" => Some('&'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ => {
+            let code =
+                if let Some(hex) = name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(decimal) = name.strip_prefix('#') {
+                    decimal.parse().ok()
+                } else {
+                    None
+                };
+            code.and_then(char::from_u32)
+        }
+    };
+
+    match decoded {
+        Some(c) => (c.to_string(), end + 1),
+        None => literal,
+    }
+}
+
+/// Turns element content into line-based block content
+fn normalize_content(content: &str) -> String {
+    let content = content
+        .strip_prefix("\r\n")
+        .or_else(|| content.strip_prefix('\n'))
+        .unwrap_or(content);
+
+    // Drop the indentation in front of the closing tag
+    let content = match content.rfind('\n') {
+        Some(last_newline) if content[last_newline + 1..].trim().is_empty() => {
+            &content[..=last_newline]
+        }
+        None if content.trim().is_empty() => "",
+        _ => content,
+    };
+
+    let mut normalized = content.to_string();
+    if !normalized.is_empty() && !normalized.ends_with('\n') {
+        normalized.push('\n');
+    }
+    normalized
+}
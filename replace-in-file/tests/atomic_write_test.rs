@@ -0,0 +1,137 @@
+use replace_in_file::{
+    DiffError, DiffOptions, EditorCommand, EditorTool, apply_to_path, write_to_file,
+};
+use std::fs;
+use std::path::Path;
+
+// Atomic file replacement keeping permissions and symlinks
+
+const DIFF: &str = "------- SEARCH
+echo one
+=======
+echo two
++++++++ REPLACE";
+
+fn entries(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_apply_leaves_no_temporary_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("run.sh");
+    fs::write(&path, "echo one\n").unwrap();
+
+    let options = DiffOptions {
+        sync_writes: true,
+        ..Default::default()
+    };
+    apply_to_path(&path, DIFF, &options).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "echo two\n");
+    assert_eq!(entries(dir.path()), ["run.sh"]);
+
+    // A failing diff touches nothing
+    let result = apply_to_path(&path, DIFF, &options);
+    assert!(matches!(result, Err(DiffError::SearchBlockNotFound(_))));
+    assert_eq!(entries(dir.path()), ["run.sh"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_permissions_are_kept() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("run.sh");
+    fs::write(&path, "echo one\n").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o754)).unwrap();
+
+    apply_to_path(&path, DIFF, &DiffOptions::default()).unwrap();
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o754);
+
+    write_to_file(&path, "echo three\n", false).unwrap();
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o754);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlinks_keep_pointing_to_the_edited_target() {
+    use std::os::unix::fs::symlink;
+
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("scripts")).unwrap();
+    let target = dir.path().join("scripts/run.sh");
+    fs::write(&target, "echo one\n").unwrap();
+    let link = dir.path().join("run.sh");
+    symlink("scripts/run.sh", &link).unwrap();
+
+    apply_to_path(&link, DIFF, &DiffOptions::default()).unwrap();
+    assert!(
+        fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink()
+    );
+    assert_eq!(fs::read_to_string(&target).unwrap(), "echo two\n");
+    assert_eq!(entries(&dir.path().join("scripts")), ["run.sh"]);
+
+    // Writing through a dangling link creates its target
+    symlink("scripts/new.sh", dir.path().join("new.sh")).unwrap();
+    write_to_file(&dir.path().join("new.sh"), "echo new\n", false).unwrap();
+    assert_eq!(
+        fs::read_to_string(dir.path().join("scripts/new.sh")).unwrap(),
+        "echo new\n"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_editor_tool_edits_are_atomic() {
+    use std::os::unix::fs::{PermissionsExt, symlink};
+
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("scripts")).unwrap();
+    let target = dir.path().join("scripts/run.sh");
+    fs::write(&target, "echo one\n").unwrap();
+    fs::set_permissions(&target, fs::Permissions::from_mode(0o754)).unwrap();
+    let link = dir.path().join("run.sh");
+    symlink("scripts/run.sh", &link).unwrap();
+    let path = link.to_string_lossy().into_owned();
+
+    let mut tool = EditorTool::new();
+    tool.execute(&EditorCommand::ReplaceInFile {
+        path: path.clone(),
+        diff: DIFF.to_string(),
+        expected_sha256: None,
+    })
+    .unwrap();
+    tool.execute(&EditorCommand::Insert {
+        path: path.clone(),
+        insert_line: 1,
+        new_str: "echo three\n".to_string(),
+    })
+    .unwrap();
+    assert_eq!(
+        fs::read_to_string(&target).unwrap(),
+        "echo two\necho three\n"
+    );
+    tool.execute(&EditorCommand::UndoEdit { path }).unwrap();
+    assert_eq!(fs::read_to_string(&target).unwrap(), "echo two\n");
+
+    assert!(
+        fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink()
+    );
+    let mode = fs::metadata(&target).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o754);
+    assert_eq!(entries(&dir.path().join("scripts")), ["run.sh"]);
+}
@@ -0,0 +1,88 @@
+use replace_in_file::{
+    DiffError, construct_new_file_content_v1_bytes, construct_new_file_content_v2_bytes,
+};
+
+// Editing files that aren't valid UTF-8 through the byte-slice API
+
+/// "caf\xe9" is "café" in Latin-1
+const LATIN1: &[u8] = b"let name = \"caf\xe9\";\nlet count = 1;\nlet total = 2;\n";
+
+#[test]
+fn test_latin1_bytes_outside_the_edit_are_kept() {
+    let diff = "------- SEARCH
+let count = 1;
+=======
+let count = 10;
++++++++ REPLACE";
+    let expected = b"let name = \"caf\xe9\";\nlet count = 10;\nlet total = 2;\n";
+    assert_eq!(
+        construct_new_file_content_v2_bytes(diff, LATIN1, true).unwrap(),
+        expected
+    );
+    assert_eq!(
+        construct_new_file_content_v1_bytes(diff, LATIN1, true).unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn test_lines_with_invalid_bytes_can_be_replaced_through_anchors() {
+    let original = b"fn a() {\n    x(\"\xff\xfe\");\n}\n";
+    let diff = "------- SEARCH
+fn a() {
+    ...
+}
+=======
+fn a() {}
++++++++ REPLACE";
+    assert_eq!(
+        construct_new_file_content_v2_bytes(diff, original, true).unwrap(),
+        b"fn a() {}\n"
+    );
+}
+
+#[test]
+fn test_invalid_bytes_are_never_matched_by_diff_text() {
+    let diff = "------- SEARCH
+let name = \"café\";
+=======
+let name = \"tea\";
++++++++ REPLACE";
+    assert!(matches!(
+        construct_new_file_content_v2_bytes(diff, LATIN1, true),
+        Err(DiffError::SearchBlockNotFound(_))
+    ));
+}
+
+#[test]
+fn test_mixed_encodings_and_escape_lookalikes_round_trip() {
+    // UTF-8 text, a stray Latin-1 byte and a private use character encoded in UTF-8
+    let original = "é \u{10FFE9}\n"
+        .bytes()
+        .chain(*b"\xe9\nend\n")
+        .collect::<Vec<u8>>();
+    let diff = "------- SEARCH
+end
+=======
+é \u{10FFE9}
++++++++ REPLACE";
+
+    let mut expected = original.clone();
+    expected.truncate(original.len() - "end\n".len());
+    expected.extend_from_slice("é \u{10FFE9}\n".as_bytes());
+    assert_eq!(
+        construct_new_file_content_v2_bytes(diff, &original, true).unwrap(),
+        expected
+    );
+
+    // The private use character in the diff matches the one in the file
+    let diff = "------- SEARCH
+é \u{10FFE9}
+=======
+ok
++++++++ REPLACE";
+    assert_eq!(
+        construct_new_file_content_v2_bytes(diff, &original, true).unwrap(),
+        b"ok\n\xe9\nend\n"
+    );
+}
@@ -0,0 +1,183 @@
+use proptest::prelude::*;
+use replace_in_file::{BlockDependency, DiffOptions, compose_diffs, construct_new_file_content_v2};
+
+// Composing sequential diffs into one diff against the original
+
+const ORIGINAL: &str = "fn a() {
+    one();
+}
+
+fn b() {
+    two();
+}
+";
+
+fn compose(first: &str, second: &str, original: &str) -> replace_in_file::Composition {
+    let composition = compose_diffs(first, second, original, &DiffOptions::default()).unwrap();
+    let sequential = construct_new_file_content_v2(
+        second,
+        &construct_new_file_content_v2(first, original, true).unwrap(),
+        true,
+    )
+    .unwrap();
+    assert_eq!(
+        construct_new_file_content_v2(&composition.diff, original, true).unwrap(),
+        sequential,
+        "composed diff:\n{}",
+        composition.diff
+    );
+    composition
+}
+
+#[test]
+fn test_independent_diffs() {
+    let first = "------- SEARCH\n    one();\n=======\n    uno();\n+++++++ REPLACE";
+    let second = "------- SEARCH\n    two();\n=======\n    dos();\n+++++++ REPLACE";
+    let composition = compose(first, second, ORIGINAL);
+    assert!(composition.dependencies.is_empty());
+    assert_eq!(
+        composition.diff,
+        "------- SEARCH
+    one();
+=======
+    uno();
++++++++ REPLACE
+------- SEARCH
+    two();
+=======
+    dos();
++++++++ REPLACE
+"
+    );
+}
+
+#[test]
+fn test_second_diff_editing_text_of_the_first() {
+    let first = "------- SEARCH AFTER\n    one();\n=======\n    helper();\n+++++++ REPLACE";
+    let second = "------- SEARCH\n    two();\n=======\n    dos();\n+++++++ REPLACE
+------- SEARCH\n    helper();\n=======\n    helper(1);\n+++++++ REPLACE";
+    let composition = compose(first, second, ORIGINAL);
+    assert_eq!(
+        composition.dependencies,
+        vec![BlockDependency {
+            first: 0,
+            second: 1
+        }]
+    );
+    // The inserted line shows up as written in the end
+    assert!(composition.diff.contains("    one();\n    helper(1);\n"));
+}
+
+#[test]
+fn test_second_diff_reverting_the_first() {
+    let first = "------- SEARCH\n    one();\n=======\n    uno();\n+++++++ REPLACE";
+    let second = "------- SEARCH\n    uno();\n=======\n    one();\n+++++++ REPLACE";
+    let composition = compose(first, second, ORIGINAL);
+    assert_eq!(composition.diff, "");
+    assert_eq!(composition.dependencies.len(), 1);
+}
+
+#[test]
+fn test_repeated_lines_get_leading_context() {
+    let original = "a\nx\nb\nx\n";
+    let first = "------- SEARCH\nb\n=======\nB\n+++++++ REPLACE";
+    let second = "------- SEARCH\nB\nx\n=======\nB\ny\n+++++++ REPLACE";
+    let composition = compose(first, second, original);
+    assert_eq!(
+        composition.diff,
+        "------- SEARCH\nb\nx\n=======\nB\ny\n+++++++ REPLACE\n"
+    );
+}
+
+#[test]
+fn test_reverted_region_before_a_repeated_line() {
+    let original = "foo\nbar\nfoo\n";
+    let first = "------- SEARCH\nfoo\n=======\nFOO\n+++++++ REPLACE";
+    let second = "------- SEARCH\nFOO\n=======\nfoo\n+++++++ REPLACE
+------- SEARCH\nfoo\n=======\nbaz\n+++++++ REPLACE";
+    let composition = compose(first, second, original);
+    assert_eq!(
+        construct_new_file_content_v2(&composition.diff, original, true).unwrap(),
+        "foo\nbar\nbaz\n"
+    );
+}
+
+#[test]
+fn test_joined_lines() {
+    // Removing the end of the first line joins the second one to it
+    let original = "  a\nb\nc\n";
+    let first = "------- SEARCH\na\n=======\n+++++++ REPLACE";
+    let second = "------- SEARCH\nc\n=======\nC\n+++++++ REPLACE";
+    let composition = compose(first, second, original);
+    assert_eq!(
+        construct_new_file_content_v2(&composition.diff, original, true).unwrap(),
+        "  b\nC\n"
+    );
+}
+
+/// Builds a diff replacing the given line ranges of `content`, the first line from the
+/// given byte on so that lines get joined, dropping blocks whose SEARCH content would be
+/// blank, ambiguous or overlap an earlier one
+fn diff_for(content: &str, edits: &[(usize, usize, usize, Vec<&str>)]) -> String {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let mut diff = String::new();
+    let mut next_line = 0;
+    for (start, skip, count, replace) in edits {
+        if lines.is_empty() {
+            break;
+        }
+        let start = start % lines.len();
+        let end = (start + count % 3 + 1).min(lines.len());
+        let skip = skip % lines[start].len();
+        let search = &lines[start..end].concat()[skip..];
+        let offset: usize = lines[..start].iter().map(|l| l.len()).sum::<usize>() + skip;
+        if start < next_line || search.trim().is_empty() || content.find(search) != Some(offset) {
+            continue;
+        }
+        next_line = end;
+        diff.push_str("------- SEARCH\n");
+        diff.push_str(search);
+        diff.push_str("=======\n");
+        for line in replace {
+            diff.push_str(line);
+            diff.push('\n');
+        }
+        diff.push_str("+++++++ REPLACE\n");
+    }
+    diff
+}
+
+fn line() -> impl Strategy<Value = &'static str> {
+    prop::sample::select(vec!["a", "b", "c", "    d", "}", ""])
+}
+
+fn edits() -> impl Strategy<Value = Vec<(usize, usize, usize, Vec<&'static str>)>> {
+    prop::collection::vec(
+        (
+            any::<usize>(),
+            prop_oneof![3 => Just(0), 1 => any::<usize>()],
+            any::<usize>(),
+            prop::collection::vec(line(), 0..3),
+        ),
+        0..4,
+    )
+}
+
+proptest! {
+    #[test]
+    fn composed_diff_matches_sequential_application(
+        original in prop::collection::vec(line(), 0..12),
+        first_edits in edits(),
+        second_edits in edits(),
+    ) {
+        let original: String = original.iter().map(|line| format!("{line}\n")).collect();
+        let first = diff_for(&original, &first_edits);
+        let intermediate = construct_new_file_content_v2(&first, &original, true).unwrap();
+        // Composed diffs don't represent a missing final newline
+        prop_assume!(intermediate.is_empty() || intermediate.ends_with('\n'));
+        let second = diff_for(&intermediate, &second_edits);
+        let updated = construct_new_file_content_v2(&second, &intermediate, true).unwrap();
+        prop_assume!(updated.is_empty() || updated.ends_with('\n'));
+        compose(&first, &second, &original);
+    }
+}
@@ -270,7 +270,7 @@ fn test_missing_final_replace_marker_with_multiple_lines() {
     assert_eq!(result, expected);
 }
 
-// Out-of-order replacements (TS v1 expects success; Rust v2 is strict → errors)
+// Out-of-order replacements (resolved against the original and applied in file order)
 #[test]
 fn test_out_of_order_replacements_different_positions() {
     let original = "first\nsecond\nthird\nfourth\n";
@@ -286,7 +286,7 @@ new second
 ++++++ REPLACE";
 
     let result = construct_new_file_content_v2(diff, original, true);
-    assert!(result.is_err());
+    assert_eq!(result.unwrap(), "first\nnew second\nthird\nnew fourth\n");
 }
 
 #[test]
@@ -309,7 +309,7 @@ fifth
 ++++++ REPLACE";
 
     let result = construct_new_file_content_v2(diff, original, true);
-    assert!(result.is_err());
+    assert_eq!(result.unwrap(), "one\nsecond\nthree\nfourth\nfifth\n");
 }
 
 #[test]
@@ -327,7 +327,10 @@ fn test_out_of_order_with_indentation() {
 ++++++ REPLACE";
 
     let result = construct_new_file_content_v2(diff, original, true);
-    assert!(result.is_err());
+    assert_eq!(
+        result.unwrap(),
+        "function test() {\n\tconst a = 10;\n\tconst b = 2;\n\tconst c = 30;\n\n}"
+    );
 }
 
 #[test]
@@ -347,5 +350,5 @@ new body content
 ++++++ REPLACE";
 
     let result = construct_new_file_content_v2(diff, original, true);
-    assert!(result.is_err());
+    assert_eq!(result.unwrap(), "header\nnew body content\nnew footer\n");
 }
\ No newline at end of file
@@ -0,0 +1,219 @@
+use replace_in_file::{DiffError, EditorCommand, EditorTool};
+use std::fs;
+
+// Text-editor tool command set on top of the apply functions
+
+fn command(json: &str) -> EditorCommand {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn test_commands_deserialize_from_tool_calls() {
+    assert_eq!(
+        command(r#"{"command": "view", "path": "a.txt", "view_range": [2, -1]}"#),
+        EditorCommand::View {
+            path: "a.txt".to_string(),
+            view_range: Some((2, -1)),
+        }
+    );
+    assert!(matches!(
+        command(r#"{"command": "str_replace", "path": "a.txt", "old_str": "x", "new_str": "y"}"#),
+        EditorCommand::StrReplace(ref request) if request.old_str == "x" && !request.replace_all
+    ));
+    assert_eq!(
+        command(r#"{"command": "undo_edit", "path": "a.txt"}"#),
+        EditorCommand::UndoEdit {
+            path: "a.txt".to_string()
+        }
+    );
+}
+
+#[test]
+fn test_view_file_with_line_numbers_and_range() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a.txt");
+    fs::write(&path, "one\ntwo\nthree\n").unwrap();
+    let path = path.to_string_lossy().to_string();
+    let tool = &mut EditorTool::new();
+
+    let all = tool
+        .execute(&EditorCommand::View {
+            path: path.clone(),
+            view_range: None,
+        })
+        .unwrap();
+    assert_eq!(all, "     1\tone\n     2\ttwo\n     3\tthree");
+
+    let range = tool
+        .execute(&EditorCommand::View {
+            path: path.clone(),
+            view_range: Some((2, -1)),
+        })
+        .unwrap();
+    assert_eq!(range, "     2\ttwo\n     3\tthree");
+
+    let invalid = tool.execute(&EditorCommand::View {
+        path,
+        view_range: Some((3, 2)),
+    });
+    assert!(matches!(invalid, Err(DiffError::InvalidViewRange(_))));
+}
+
+#[test]
+fn test_view_directory_lists_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+    fs::write(dir.path().join(".hidden"), "").unwrap();
+
+    let listing = EditorTool::new()
+        .execute(&EditorCommand::View {
+            path: dir.path().to_string_lossy().to_string(),
+            view_range: None,
+        })
+        .unwrap();
+    assert!(listing.contains("src/lib.rs"));
+    assert!(!listing.contains(".hidden"));
+}
+
+#[test]
+fn test_create_refuses_to_overwrite_and_undo_removes_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nested/new.txt");
+    let path_str = path.to_string_lossy().to_string();
+    let tool = &mut EditorTool::new();
+
+    tool.execute(&EditorCommand::Create {
+        path: path_str.clone(),
+        file_text: "hello\n".to_string(),
+        overwrite: false,
+    })
+    .unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "hello\n");
+
+    let again = tool.execute(&EditorCommand::Create {
+        path: path_str.clone(),
+        file_text: "bye\n".to_string(),
+        overwrite: false,
+    });
+    assert!(matches!(again, Err(DiffError::FileAlreadyExists(_))));
+
+    tool.execute(&EditorCommand::UndoEdit { path: path_str })
+        .unwrap();
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_insert_after_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a.txt");
+    fs::write(&path, "one\ntwo").unwrap();
+    let path_str = path.to_string_lossy().to_string();
+    let tool = &mut EditorTool::new();
+
+    tool.execute(&EditorCommand::Insert {
+        path: path_str.clone(),
+        insert_line: 1,
+        new_str: "one and a half".to_string(),
+    })
+    .unwrap();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "one\none and a half\ntwo"
+    );
+
+    tool.execute(&EditorCommand::Insert {
+        path: path_str.clone(),
+        insert_line: 3,
+        new_str: "three".to_string(),
+    })
+    .unwrap();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "one\none and a half\ntwo\nthree"
+    );
+
+    tool.execute(&EditorCommand::Insert {
+        path: path_str.clone(),
+        insert_line: 0,
+        new_str: "zero\n".to_string(),
+    })
+    .unwrap();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "zero\none\none and a half\ntwo\nthree"
+    );
+
+    let out_of_range = tool.execute(&EditorCommand::Insert {
+        path: path_str,
+        insert_line: 10,
+        new_str: "x".to_string(),
+    });
+    assert!(matches!(
+        out_of_range,
+        Err(DiffError::InvalidInsertLine {
+            line: 10,
+            line_count: 5
+        })
+    ));
+}
+
+#[test]
+fn test_edits_are_undone_in_reverse_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a.txt");
+    fs::write(&path, "alpha\nbeta\n").unwrap();
+    let path_str = path.to_string_lossy().to_string();
+    let tool = &mut EditorTool::new();
+
+    tool.execute(&command(&format!(
+        r#"{{"command": "str_replace", "path": {path_str:?}, "old_str": "alpha", "new_str": "ALPHA"}}"#
+    )))
+    .unwrap();
+    tool.execute(&EditorCommand::ReplaceInFile {
+        path: path_str.clone(),
+        diff: "------- SEARCH\nbeta\n=======\nBETA\n+++++++ REPLACE".to_string(),
+        expected_sha256: None,
+    })
+    .unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "ALPHA\nBETA\n");
+
+    let undo = EditorCommand::UndoEdit { path: path_str };
+    tool.execute(&undo).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "ALPHA\nbeta\n");
+    tool.execute(&undo).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "alpha\nbeta\n");
+    assert!(matches!(
+        tool.execute(&undo),
+        Err(DiffError::NoEditHistory(_))
+    ));
+}
+
+#[test]
+fn test_failed_edit_leaves_file_and_history_untouched() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a.txt");
+    fs::write(&path, "same\nsame\n").unwrap();
+    let path_str = path.to_string_lossy().to_string();
+    let tool = &mut EditorTool::new();
+
+    let result = tool.execute(&command(&format!(
+        r#"{{"command": "str_replace", "path": {path_str:?}, "old_str": "same", "new_str": "other"}}"#
+    )));
+    assert!(matches!(result, Err(DiffError::OldStrNotUnique { .. })));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "same\nsame\n");
+    assert!(matches!(
+        tool.execute(&EditorCommand::UndoEdit { path: path_str }),
+        Err(DiffError::NoEditHistory(_))
+    ));
+}
+
+#[test]
+fn test_missing_file_is_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let result = EditorTool::new().execute(&EditorCommand::View {
+        path: dir.path().join("nope.txt").to_string_lossy().to_string(),
+        view_range: None,
+    });
+    assert!(matches!(result, Err(DiffError::PathNotFound(_))));
+}
@@ -0,0 +1,121 @@
+use replace_in_file::construct_new_file_content_v2;
+
+// Elision-aware SEARCH blocks: `...` markers act as wildcard gaps between anchors
+
+#[test]
+fn test_elided_search_replaces_whole_span() {
+    let original = "fn a() {}\nfn main() {\n    let x = 1;\n    let y = 2;\n    println!(\"{}\", x + y);\n}\nfn b() {}\n";
+    let diff = "------- SEARCH
+fn main() {
+...
+}
+=======
+fn main() {}
++++++++ REPLACE";
+    let expected = "fn a() {}\nfn main() {}\nfn b() {}\n";
+
+    let result = construct_new_file_content_v2(diff, original, true).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_elided_search_with_multi_line_anchors() {
+    let original = "start\nkeep\nhead1\nhead2\nbody1\nbody2\ntail1\ntail2\nend\n";
+    let diff = "------- SEARCH
+head1
+head2
+    // ... existing code ...
+tail1
+tail2
+=======
+replaced
++++++++ REPLACE";
+    let expected = "start\nkeep\nreplaced\nend\n";
+
+    let result = construct_new_file_content_v2(diff, original, true).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_elided_search_with_several_gaps() {
+    let original = "a\nb\nx\nc\ny\nz\nd\ne\n";
+    let diff = "------- SEARCH
+a
+...
+c
+# ...
+d
+=======
+merged
++++++++ REPLACE";
+    let expected = "merged\ne\n";
+
+    let result = construct_new_file_content_v2(diff, original, true).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_elided_search_suffix_must_follow_prefix() {
+    let original = "tail\nhead\nbody\n";
+    let diff = "------- SEARCH
+head
+...
+tail
+=======
+replaced
++++++++ REPLACE";
+
+    assert!(construct_new_file_content_v2(diff, original, true).is_err());
+}
+
+#[test]
+fn test_elided_search_requires_anchors_on_both_sides() {
+    let original = "head\nbody\ntail\n";
+    let diff = "------- SEARCH
+...
+tail
+=======
+replaced
++++++++ REPLACE";
+
+    assert!(construct_new_file_content_v2(diff, original, true).is_err());
+}
+
+#[test]
+fn test_literal_ellipsis_still_matches_exactly() {
+    let original = "def stub():\n    ...\n\nx = 1\n";
+    let diff = "------- SEARCH
+def stub():
+    ...
+=======
+def stub():
+    return 1
++++++++ REPLACE";
+    let expected = "def stub():\n    return 1\n\nx = 1\n";
+
+    let result = construct_new_file_content_v2(diff, original, true).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_elided_search_respects_last_processed_index() {
+    let original = "begin\nold\nend\nbegin\nold\nend\n";
+    let diff = "------- SEARCH
+begin
+old
+end
+=======
+first
++++++++ REPLACE
+------- SEARCH
+begin
+...
+end
+=======
+second
++++++++ REPLACE";
+    let expected = "first\nsecond\n";
+
+    let result = construct_new_file_content_v2(diff, original, true).unwrap();
+    assert_eq!(result, expected);
+}
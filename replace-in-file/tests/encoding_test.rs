@@ -0,0 +1,127 @@
+use replace_in_file::{
+    DiffError, DiffOptions, TextEncoding, apply_to_path, decode_text, detect_encoding, encode_text,
+    write_to_file,
+};
+use std::fs;
+
+// Encoding detection and round trips through the file-level API
+
+const DIFF: &str = "------- SEARCH
+café = 1
+=======
+café = 2
++++++++ REPLACE";
+
+fn utf16le_with_bom(text: &str) -> Vec<u8> {
+    let mut content = vec![0xFF, 0xFE];
+    content.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+    content
+}
+
+#[test]
+fn test_detect_encoding() {
+    assert_eq!(detect_encoding(b""), TextEncoding::Utf8 { bom: false });
+    assert_eq!(
+        detect_encoding("é\n".as_bytes()),
+        TextEncoding::Utf8 { bom: false }
+    );
+    assert_eq!(
+        detect_encoding(b"\xEF\xBB\xBFx"),
+        TextEncoding::Utf8 { bom: true }
+    );
+    assert_eq!(
+        detect_encoding(&utf16le_with_bom("x")),
+        TextEncoding::Utf16Le { bom: true }
+    );
+    assert_eq!(
+        detect_encoding(b"\xFE\xFF\x00x"),
+        TextEncoding::Utf16Be { bom: true }
+    );
+    assert_eq!(
+        detect_encoding(b"a\x00b\x00\n\x00"),
+        TextEncoding::Utf16Le { bom: false }
+    );
+    assert_eq!(
+        detect_encoding(b"\x00a\x00b\x00\n"),
+        TextEncoding::Utf16Be { bom: false }
+    );
+    assert_eq!(
+        detect_encoding(b"caf\xe9 \x80\n"),
+        TextEncoding::Windows1252
+    );
+    // 0x81 is undefined in Windows-1252
+    assert_eq!(detect_encoding(b"caf\xe9 \x81\n"), TextEncoding::Latin1);
+}
+
+#[test]
+fn test_every_encoding_round_trips() {
+    let samples: [&[u8]; 7] = [
+        "plain ü\n".as_bytes(),
+        b"\xEF\xBB\xBFwith bom\r\n",
+        b"\xFF\xFEh\x00i\x00=\xd8\x00\xde",
+        b"\x00h\x00i",
+        b"\xFE\xFF\x00h\x00i",
+        b"\x93quoted\x94 \x80 caf\xe9",
+        b"\x81 caf\xe9",
+    ];
+    for sample in samples {
+        let (text, encoding) = decode_text(sample).unwrap();
+        assert_eq!(encode_text(&text, encoding).unwrap(), sample, "{encoding}");
+    }
+    assert_eq!(decode_text(b"\x93q\x94").unwrap().0, "\u{201C}q\u{201D}");
+}
+
+#[test]
+fn test_apply_keeps_utf16_and_its_bom() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("Strings.resx");
+    fs::write(&path, utf16le_with_bom("x = 0\r\ncafé = 1\n")).unwrap();
+
+    let outcome = apply_to_path(&path, DIFF, &DiffOptions::default()).unwrap();
+    assert_eq!(outcome.encoding, TextEncoding::Utf16Le { bom: true });
+    assert_eq!(outcome.content, "x = 0\r\ncafé = 2\n");
+    assert_eq!(
+        fs::read(&path).unwrap(),
+        utf16le_with_bom("x = 0\r\ncafé = 2\n")
+    );
+}
+
+#[test]
+fn test_apply_to_single_byte_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("legacy.ini");
+    fs::write(&path, b"caf\xe9 = 1\n").unwrap();
+
+    let outcome = apply_to_path(&path, DIFF, &DiffOptions::default()).unwrap();
+    assert_eq!(outcome.encoding, TextEncoding::Windows1252);
+    assert_eq!(fs::read(&path).unwrap(), b"caf\xe9 = 2\n");
+
+    // Characters the encoding lacks are refused instead of being mangled
+    let diff = "------- SEARCH\ncafé = 2\n=======\ncafé = 🎉\n+++++++ REPLACE";
+    let result = apply_to_path(&path, diff, &DiffOptions::default());
+    assert!(matches!(
+        result,
+        Err(DiffError::UnencodableCharacter {
+            character: '🎉',
+            encoding: TextEncoding::Windows1252
+        })
+    ));
+    assert_eq!(fs::read(&path).unwrap(), b"caf\xe9 = 2\n");
+}
+
+#[test]
+fn test_apply_to_missing_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let result = apply_to_path(&dir.path().join("nope.txt"), DIFF, &DiffOptions::default());
+    assert!(matches!(result, Err(DiffError::PathNotFound(_))));
+}
+
+#[test]
+fn test_write_to_file_keeps_the_existing_encoding() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("bom.cs");
+    fs::write(&path, b"\xEF\xBB\xBFclass A {}\n").unwrap();
+
+    write_to_file(&path, "class B {}\n", false).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"\xEF\xBB\xBFclass B {}\n");
+}
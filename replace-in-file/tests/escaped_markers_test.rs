@@ -0,0 +1,103 @@
+use replace_in_file::construct_new_file_content_v2;
+
+// Marker collisions: content lines that look like markers are escaped with a
+// leading backslash, which is removed when the block is applied
+
+#[test]
+fn test_escaped_separator_in_merge_conflict_example() {
+    let original = "<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> branch\n";
+    let diff = "------- SEARCH
+ours
+\\=======
+theirs
+=======
+mine
+\\=======
+yours
++++++++ REPLACE";
+    let expected = "<<<<<<< HEAD\nmine\n=======\nyours\n>>>>>>> branch\n";
+
+    let result = construct_new_file_content_v2(diff, original, true).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_escaped_rst_heading() {
+    let original = "Title\n=====\n\nSection\n-------\n";
+    let diff = "------- SEARCH
+Title
+\\=====
+=======
+New Title
+\\=========
++++++++ REPLACE";
+    let expected = "New Title\n=========\n\nSection\n-------\n";
+
+    let result = construct_new_file_content_v2(diff, original, true).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_escaped_search_and_replace_markers() {
+    let original = "let diff = \"\n------- SEARCH\nold\n=======\nnew\n+++++++ REPLACE\";\n";
+    let diff = "------- SEARCH
+\\------- SEARCH
+old
+\\=======
+new
++++++++ REPLACE\";
+=======
+\\------- SEARCH
+old
+\\=======
+newer
++++++++ REPLACE\";
++++++++ REPLACE";
+    let expected = "let diff = \"\n------- SEARCH\nold\n=======\nnewer\n+++++++ REPLACE\";\n";
+
+    let result = construct_new_file_content_v2(diff, original, true).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_double_escape_yields_literal_escaped_marker() {
+    let original = "a\n\\=======\nb\n";
+    let diff = "------- SEARCH
+\\\\=======
+=======
+\\\\=======
+c
++++++++ REPLACE";
+    let expected = "a\n\\=======\nc\nb\n";
+
+    let result = construct_new_file_content_v2(diff, original, true).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_other_backslash_lines_are_untouched() {
+    let original = "\\section{Intro}\ntext\n";
+    let diff = "------- SEARCH
+\\section{Intro}
+=======
+\\section{Introduction}
++++++++ REPLACE";
+    let expected = "\\section{Introduction}\ntext\n";
+
+    let result = construct_new_file_content_v2(diff, original, true).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_unescaped_separator_in_content_still_splits_block() {
+    let original = "ours\n=======\ntheirs\n";
+    let diff = "------- SEARCH
+ours
+=======
+theirs
+=======
+mine
++++++++ REPLACE";
+
+    assert!(construct_new_file_content_v2(diff, original, true).is_err());
+}
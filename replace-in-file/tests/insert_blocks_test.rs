@@ -0,0 +1,152 @@
+use replace_in_file::{
+    BlockKind, DiffError, DiffOptions, construct_new_file_content_v2,
+    construct_new_file_content_v2_with_report, parse_search_replace_blocks,
+};
+
+// BEFORE / AFTER / PREPEND / APPEND insertion blocks
+
+const ORIGINAL: &str = "use std::fs;\n\nfn main() {\n    run();\n}\n";
+
+#[test]
+fn test_insert_after_anchor() {
+    let diff = "------- SEARCH AFTER\nuse std::fs;\n=======\nuse std::io;\n+++++++ REPLACE";
+    let report =
+        construct_new_file_content_v2_with_report(diff, ORIGINAL, true, &DiffOptions::default())
+            .unwrap();
+    assert_eq!(
+        report.content,
+        "use std::fs;\nuse std::io;\n\nfn main() {\n    run();\n}\n"
+    );
+    assert_eq!(report.blocks[0].ranges, vec![13..13]);
+}
+
+#[test]
+fn test_insert_before_anchor_with_fuzzy_match() {
+    let diff = "------- SEARCH BEFORE\nrun();\n=======\n    setup();\n+++++++ REPLACE";
+    let result = construct_new_file_content_v2(diff, ORIGINAL, true).unwrap();
+    assert_eq!(
+        result,
+        "use std::fs;\n\nfn main() {\n    setup();\n    run();\n}\n"
+    );
+}
+
+#[test]
+fn test_anchor_can_be_edited_by_a_later_block() {
+    let diff = "------- SEARCH BEFORE
+    run();
+=======
+    setup();
++++++++ REPLACE
+------- SEARCH
+    run();
+=======
+    run_all();
++++++++ REPLACE";
+    let result = construct_new_file_content_v2(diff, ORIGINAL, true).unwrap();
+    assert_eq!(
+        result,
+        "use std::fs;\n\nfn main() {\n    setup();\n    run_all();\n}\n"
+    );
+}
+
+#[test]
+fn test_prepend_and_append() {
+    let diff = "------- SEARCH
+fn main() {
+=======
+pub fn main() {
++++++++ REPLACE
+------- SEARCH PREPEND
+=======
+//! Entry point
++++++++ REPLACE
+------- SEARCH APPEND
+=======
+
+fn run() {}
++++++++ REPLACE";
+    let result = construct_new_file_content_v2(diff, ORIGINAL, true).unwrap();
+    assert_eq!(
+        result,
+        "//! Entry point\nuse std::fs;\n\npub fn main() {\n    run();\n}\n\nfn run() {}\n"
+    );
+}
+
+#[test]
+fn test_append_to_file_without_final_newline() {
+    let diff = "------- SEARCH APPEND\n=======\nb\n+++++++ REPLACE";
+    assert_eq!(
+        construct_new_file_content_v2(diff, "a", true).unwrap(),
+        "a\nb\n"
+    );
+
+    let diff = "------- SEARCH AFTER\na\n=======\nb\n+++++++ REPLACE";
+    assert_eq!(
+        construct_new_file_content_v2(diff, "a", true).unwrap(),
+        "a\nb\n"
+    );
+}
+
+#[test]
+fn test_insert_after_every_regex_match() {
+    let original = "#[test]\nfn a() {}\n#[test]\nfn b() {}\n";
+    let diff = r"------- SEARCH AFTER REGEX ALL
+fn (\w+)
+=======
+// end of $1
++++++++ REPLACE";
+    let result = construct_new_file_content_v2(diff, original, true).unwrap();
+    assert_eq!(
+        result,
+        "#[test]\nfn a() {}\n// end of a\n#[test]\nfn b() {}\n// end of b\n"
+    );
+}
+
+#[test]
+fn test_invalid_insertion_blocks() {
+    let blocks = parse_search_replace_blocks(
+        "------- SEARCH AFTER\nx\n=======\ny\n+++++++ REPLACE",
+        &DiffOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(blocks[0].modifiers.kind, BlockKind::InsertAfter);
+
+    let no_anchor = "------- SEARCH AFTER\n=======\ny\n+++++++ REPLACE";
+    assert!(matches!(
+        construct_new_file_content_v2(no_anchor, ORIGINAL, true),
+        Err(DiffError::InvalidBlockModifiers(_))
+    ));
+
+    let with_search = "------- SEARCH APPEND\nfn main() {\n=======\ny\n+++++++ REPLACE";
+    assert!(matches!(
+        construct_new_file_content_v2(with_search, ORIGINAL, true),
+        Err(DiffError::InvalidBlockModifiers(_))
+    ));
+
+    // Two kinds make the line content rather than a marker
+    let blocks = parse_search_replace_blocks(
+        "------- SEARCH\n------- SEARCH BEFORE AFTER\n=======\n+++++++ REPLACE",
+        &DiffOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(blocks[0].search, "------- SEARCH BEFORE AFTER\n");
+}
+
+#[test]
+fn test_insertion_point_inside_an_earlier_block() {
+    // The anchor is untouched but the start of its line was replaced
+    let diff = "------- SEARCH REGEX
+a\nb
+=======
+Z
++++++++ REPLACE
+------- SEARCH BEFORE
+c
+=======
+INS
++++++++ REPLACE";
+    assert!(matches!(
+        construct_new_file_content_v2(diff, "xa\nbc\n", true),
+        Err(DiffError::SearchBlockIncorrectMatch(_))
+    ));
+}
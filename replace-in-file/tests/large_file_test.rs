@@ -0,0 +1,88 @@
+use replace_in_file::{
+    DiffOptions, MatchStrategy, construct_new_file_content_v1,
+    construct_new_file_content_v2_with_report,
+};
+
+// Fuzzy matching on large generated files, where every block falls back to the line index
+
+const LINE_COUNT: usize = 50_000;
+
+fn generated_file() -> String {
+    (0..LINE_COUNT)
+        .map(|i| format!("    let v{i} = {};\n", i % 7))
+        .collect()
+}
+
+/// Blocks without the indentation of the file, so none of them matches exactly
+fn unindented_diff(step: usize) -> String {
+    (0..LINE_COUNT)
+        .step_by(step)
+        .map(|i| {
+            format!(
+                "------- SEARCH\nlet v{i} = {};\nlet v{} = {};\n=======\n    changed {i}\n+++++++ REPLACE\n",
+                i % 7,
+                i + 1,
+                (i + 1) % 7
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_many_line_trimmed_blocks() {
+    let original = generated_file();
+    let diff = unindented_diff(250);
+
+    let report =
+        construct_new_file_content_v2_with_report(&diff, &original, true, &DiffOptions::default())
+            .unwrap();
+    assert_eq!(report.blocks.len(), LINE_COUNT / 250);
+    assert!(
+        report
+            .blocks
+            .iter()
+            .all(|block| block.strategy == Some(MatchStrategy::LineTrimmed))
+    );
+    assert_eq!(
+        report.content.lines().count(),
+        LINE_COUNT - LINE_COUNT / 250
+    );
+    assert!(
+        report
+            .content
+            .contains("    changed 49750\n    let v49752 = 3;\n")
+    );
+
+    assert_eq!(
+        construct_new_file_content_v1(&diff, &original, true).unwrap(),
+        report.content
+    );
+}
+
+#[test]
+fn test_anchor_and_elided_blocks_near_the_end() {
+    let original = generated_file();
+    let diff = "------- SEARCH
+let v49990 = 3;
+the middle changed
+let v49992 = 5;
+=======
+anchored
++++++++ REPLACE
+------- SEARCH
+let v49995 = 1;
+...
+let v49998 = 4;
+=======
+elided
++++++++ REPLACE";
+
+    let report =
+        construct_new_file_content_v2_with_report(diff, &original, true, &DiffOptions::default())
+            .unwrap();
+    assert_eq!(report.blocks[0].strategy, Some(MatchStrategy::BlockAnchor));
+    assert_eq!(report.blocks[1].strategy, Some(MatchStrategy::Elided));
+    assert!(report.content.ends_with(
+        "    let v49989 = 2;\nanchored\n    let v49993 = 6;\n    let v49994 = 0;\nelided\n    let v49999 = 5;\n"
+    ));
+}
@@ -0,0 +1,164 @@
+use replace_in_file::{
+    DiffError, DiffOptions, Limits, apply_to_path, construct_new_file_content_v1_with_options,
+    construct_new_file_content_v2_with_options,
+};
+use std::fs;
+
+// Limits on untrusted diffs and the files they edit
+
+const ORIGINAL: &str = "one\ntwo\nthree\nfour\n";
+
+fn with_limits(limits: Limits) -> DiffOptions {
+    DiffOptions {
+        limits,
+        ..Default::default()
+    }
+}
+
+/// Applies the diff with both engines, which must agree
+fn apply(diff: &str, original: &str, options: &DiffOptions) -> Result<String, DiffError> {
+    let v1 = construct_new_file_content_v1_with_options(diff, original, true, options);
+    let v2 = construct_new_file_content_v2_with_options(diff, original, true, options);
+    assert_eq!(
+        format!("{v1:?}"),
+        format!("{v2:?}"),
+        "engines disagree on {diff:?}"
+    );
+    v2
+}
+
+fn block(search: &str, replace: &str) -> String {
+    format!("------- SEARCH\n{search}=======\n{replace}+++++++ REPLACE\n")
+}
+
+#[test]
+fn test_default_limits_allow_everything() {
+    let diff = block("", "new\n");
+    assert_eq!(
+        apply(&diff, ORIGINAL, &DiffOptions::default()).unwrap(),
+        "new\n"
+    );
+}
+
+#[test]
+fn test_diff_and_file_size_limits() {
+    let diff = block("two\n", "2\n");
+    let options = with_limits(Limits {
+        max_diff_bytes: Some(diff.len() - 1),
+        ..Default::default()
+    });
+    assert!(matches!(
+        apply(&diff, ORIGINAL, &options),
+        Err(DiffError::DiffTooLarge { limit, .. }) if limit == diff.len() - 1
+    ));
+
+    let options = with_limits(Limits {
+        max_file_bytes: Some(10),
+        ..Default::default()
+    });
+    assert!(matches!(
+        apply(&diff, ORIGINAL, &options),
+        Err(DiffError::FileTooLarge {
+            size: 19,
+            limit: 10
+        })
+    ));
+    assert_eq!(apply(&diff, "two\n", &options).unwrap(), "2\n");
+}
+
+#[test]
+fn test_block_count_limit() {
+    let options = with_limits(Limits {
+        max_blocks: Some(2),
+        ..Default::default()
+    });
+    let two = block("one\n", "1\n") + &block("two\n", "2\n");
+    assert_eq!(
+        apply(&two, ORIGINAL, &options).unwrap(),
+        "1\n2\nthree\nfour\n"
+    );
+
+    let three = two + &block("three\n", "3\n");
+    assert!(matches!(
+        apply(&three, ORIGINAL, &options),
+        Err(DiffError::TooManyBlocks { limit: 2 })
+    ));
+}
+
+#[test]
+fn test_deleted_fraction_limit() {
+    let options = with_limits(Limits {
+        max_deleted_fraction: Some(0.5),
+        ..Default::default()
+    });
+    // Lines kept in REPLACE aren't deleted
+    let diff = block("one\ntwo\nthree\n", "one\nzero\ntwo\nthree\n");
+    assert_eq!(
+        apply(&diff, ORIGINAL, &options).unwrap(),
+        "one\nzero\ntwo\nthree\nfour\n"
+    );
+
+    let diff = block("one\ntwo\nthree\n", "");
+    assert!(matches!(
+        apply(&diff, ORIGINAL, &options),
+        Err(DiffError::BlockDeletesTooMuch {
+            deleted: 14,
+            file_size: 19,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_same_size_rewrite_counts_as_deletion() {
+    let options = with_limits(Limits {
+        max_deleted_fraction: Some(0.25),
+        ..Default::default()
+    });
+    let diff = block(ORIGINAL, "eno\nowt\neerht\nruof\n");
+    assert!(matches!(
+        apply(&diff, ORIGINAL, &options),
+        Err(DiffError::BlockDeletesTooMuch {
+            deleted: 19,
+            file_size: 19,
+            ..
+        })
+    ));
+
+    // Rewriting a single line stays within the limit
+    let diff = block("two\n", "owt\n");
+    assert_eq!(
+        apply(&diff, ORIGINAL, &options).unwrap(),
+        "one\nowt\nthree\nfour\n"
+    );
+}
+
+#[test]
+fn test_whole_file_rewrite_can_be_forbidden() {
+    let options = with_limits(Limits {
+        forbid_whole_file_rewrite: true,
+        ..Default::default()
+    });
+    let diff = block("", "new\n");
+    assert!(matches!(
+        apply(&diff, ORIGINAL, &options),
+        Err(DiffError::WholeFileRewriteForbidden)
+    ));
+    // Creating a file is still allowed
+    assert_eq!(apply(&diff, "", &options).unwrap(), "new\n");
+}
+
+#[test]
+fn test_oversized_file_is_not_applied() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("big.txt");
+    fs::write(&path, ORIGINAL).unwrap();
+
+    let options = with_limits(Limits {
+        max_file_bytes: Some(4),
+        ..Default::default()
+    });
+    let result = apply_to_path(&path, &block("two\n", "2\n"), &options);
+    assert!(matches!(result, Err(DiffError::FileTooLarge { .. })));
+    assert_eq!(fs::read_to_string(&path).unwrap(), ORIGINAL);
+}
@@ -0,0 +1,100 @@
+use replace_in_file::{
+    BlockModifiers, DiffOptions, EditBlock, MarkerSet, construct_new_file_content_v2,
+    construct_new_file_content_v2_with_options, format_search_replace_blocks,
+    parse_search_replace_blocks,
+};
+
+// `@L<n>` hints in the SEARCH start marker choosing between repeated snippets
+
+const ORIGINAL: &str =
+    "fn a() {\n    todo!()\n}\n\nfn b() {\n    todo!()\n}\n\nfn c() {\n    todo!()\n}\n";
+
+#[test]
+fn test_hint_picks_closest_occurrence() {
+    let diff = "------- SEARCH @L6\n    todo!()\n=======\n    1\n+++++++ REPLACE";
+    let result = construct_new_file_content_v2(diff, ORIGINAL, true).unwrap();
+    assert_eq!(
+        result,
+        "fn a() {\n    todo!()\n}\n\nfn b() {\n    1\n}\n\nfn c() {\n    todo!()\n}\n"
+    );
+
+    let diff = "------- SEARCH @L11\n    todo!()\n=======\n    1\n+++++++ REPLACE";
+    let result = construct_new_file_content_v2(diff, ORIGINAL, true).unwrap();
+    assert!(result.ends_with("fn c() {\n    1\n}\n"));
+}
+
+#[test]
+fn test_stale_hint_falls_back_to_nearest_match() {
+    // Off by a few lines, or past the end of the file
+    let diff = "------- SEARCH @L8\n    todo!()\n=======\n    1\n+++++++ REPLACE";
+    let result = construct_new_file_content_v2(diff, ORIGINAL, true).unwrap();
+    assert!(result.contains("fn b() {\n    1\n}"));
+
+    let diff = "------- SEARCH @L500\n    todo!()\n=======\n    1\n+++++++ REPLACE";
+    let result = construct_new_file_content_v2(diff, ORIGINAL, true).unwrap();
+    assert!(result.ends_with("fn c() {\n    1\n}\n"));
+
+    // A unique snippet is found wherever the hint points
+    let diff = "------- SEARCH @L1\nfn c() {\n=======\nfn d() {\n+++++++ REPLACE";
+    let result = construct_new_file_content_v2(diff, ORIGINAL, true).unwrap();
+    assert!(result.contains("fn d() {"));
+}
+
+#[test]
+fn test_hint_with_fuzzy_matching() {
+    let diff = "------- SEARCH @L10\n  todo!()  \n=======\n    1\n+++++++ REPLACE";
+    let result = construct_new_file_content_v2(diff, ORIGINAL, true).unwrap();
+    assert!(result.ends_with("fn c() {\n    1\n}\n"));
+}
+
+#[test]
+fn test_hinted_blocks_stay_in_order() {
+    let diff = "------- SEARCH @L2\n    todo!()\n=======\n    1\n+++++++ REPLACE
+------- SEARCH @L10\n    todo!()\n=======\n    3\n+++++++ REPLACE";
+    let result = construct_new_file_content_v2(diff, ORIGINAL, true).unwrap();
+    assert_eq!(
+        result,
+        "fn a() {\n    1\n}\n\nfn b() {\n    todo!()\n}\n\nfn c() {\n    3\n}\n"
+    );
+}
+
+#[test]
+fn test_unknown_modifiers_are_not_markers() {
+    let blocks = parse_search_replace_blocks(
+        "------- SEARCH\n------- SEARCH here\n=======\nx\n+++++++ REPLACE",
+        &DiffOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(blocks[0].search, "------- SEARCH here\n");
+    assert!(blocks[0].modifiers.is_empty());
+}
+
+#[test]
+fn test_hint_round_trips_through_formatting() {
+    let block = EditBlock {
+        search: "    todo!()\n".to_string(),
+        replace: "    2\n".to_string(),
+        modifiers: BlockModifiers {
+            line_hint: Some(6),
+            ..Default::default()
+        },
+    };
+    let diff = format_search_replace_blocks(std::slice::from_ref(&block), &MarkerSet::standard());
+    assert!(diff.starts_with("------- SEARCH @L6\n"));
+    assert_eq!(
+        parse_search_replace_blocks(&diff, &DiffOptions::default()).unwrap(),
+        vec![block]
+    );
+}
+
+#[test]
+fn test_hint_with_custom_markers() {
+    let options = DiffOptions {
+        markers: MarkerSet::xml_tags("search", "replace"),
+        ..Default::default()
+    };
+    let diff = "<search> @L10\n    todo!()\n</search>\n<replace>\n    3\n</replace>";
+    let result =
+        construct_new_file_content_v2_with_options(diff, ORIGINAL, true, &options).unwrap();
+    assert!(result.ends_with("fn c() {\n    3\n}\n"));
+}
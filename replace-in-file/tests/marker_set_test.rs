@@ -0,0 +1,139 @@
+use replace_in_file::{
+    DiffOptions, MarkerSet, construct_new_file_content_v2,
+    construct_new_file_content_v2_with_options,
+};
+
+// Configurable marker vocabulary
+
+fn xml_options() -> DiffOptions {
+    DiffOptions {
+        markers: MarkerSet::xml_tags("search", "replace"),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_default_options_match_standard_markers() {
+    let original = "line1\nline2\nline3";
+    let diff = "------- SEARCH\nline2\n=======\nreplaced\n+++++++ REPLACE";
+
+    let r1 = construct_new_file_content_v2(diff, original, true).unwrap();
+    let r2 =
+        construct_new_file_content_v2_with_options(diff, original, true, &DiffOptions::default())
+            .unwrap();
+    assert_eq!(r1, "line1\nreplaced\nline3");
+    assert_eq!(r1, r2);
+}
+
+#[test]
+fn test_xml_like_tags() {
+    let original = "line1\nline2\nline3\nline4";
+    let diff = "<search>
+line2
+</search>
+<replace>
+replaced2
+</replace>
+
+<search>
+line4
+</search>
+<replace>
+replaced4
+</replace>";
+    let expected = "line1\nreplaced2\nline3\nreplaced4\n";
+
+    let result =
+        construct_new_file_content_v2_with_options(diff, original, true, &xml_options()).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_xml_like_tags_tolerate_indentation_and_blank_lines() {
+    let original = "a\nb\nc";
+    let diff = "  <search>
+b
+  </search>
+
+  <replace>
+B
+  </replace>";
+    let expected = "a\nB\nc";
+
+    let result =
+        construct_new_file_content_v2_with_options(diff, original, true, &xml_options()).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_custom_markers_ignore_standard_markers() {
+    let original = "x\n=======\ny";
+    let diff = "<search>
+=======
+</search>
+<replace>
+-------
+</replace>";
+    let expected = "x\n-------\ny";
+
+    let result =
+        construct_new_file_content_v2_with_options(diff, original, true, &xml_options()).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_three_marker_vocabulary() {
+    let original = "alpha\nbeta\ngamma";
+    let options = DiffOptions {
+        markers: MarkerSet::new("@@ FIND", "@@ WITH", "@@ END"),
+        ..Default::default()
+    };
+    let diff = "@@ FIND\nbeta\n@@ WITH\nBETA\n@@ END";
+    let expected = "alpha\nBETA\ngamma";
+
+    let result =
+        construct_new_file_content_v2_with_options(diff, original, true, &options).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_custom_markers_missing_final_replace_end() {
+    let original = "one\ntwo\nthree";
+    let options = DiffOptions {
+        markers: MarkerSet::new("@@ FIND", "@@ WITH", "@@ END"),
+        ..Default::default()
+    };
+    let diff = "@@ FIND\none\n@@ WITH\nONE";
+    let expected = "ONE\ntwo\nthree";
+
+    let result =
+        construct_new_file_content_v2_with_options(diff, original, true, &options).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_custom_markers_partial_trailing_marker_is_dropped() {
+    let original = "a\nb\nc";
+    let diff = "<search>\nb\n</search>\n<replace>\nB\n</rep";
+    let expected = "a\nB\n";
+
+    let result =
+        construct_new_file_content_v2_with_options(diff, original, false, &xml_options()).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_escaped_custom_marker_in_content() {
+    let original = "<search>\nkeep";
+    let diff = "<search>
+\\<search>
+</search>
+<replace>
+\\<replace>
+</replace>";
+    let expected = "<replace>\nkeep";
+
+    let result =
+        construct_new_file_content_v2_with_options(diff, original, true, &xml_options()).unwrap();
+    assert_eq!(result, expected);
+}
@@ -0,0 +1,157 @@
+use replace_in_file::{
+    DiffError, DiffOptions, construct_new_file_content_v2,
+    construct_new_file_content_v2_with_report,
+};
+
+// MOVE blocks relocating code in a single atomic edit
+
+const ORIGINAL: &str =
+    "fn helper() {\n\tprintln!(\"help\");  \n}\n\nfn main() {\n    helper();\n}\n";
+
+#[test]
+fn test_move_after_anchor() {
+    let diff = "------- SEARCH MOVE AFTER
+fn helper() {
+\tprintln!(\"help\");  
+}
+
+=======
+fn main() {
+    helper();
+}
++++++++ REPLACE";
+    let report =
+        construct_new_file_content_v2_with_report(diff, ORIGINAL, true, &DiffOptions::default())
+            .unwrap();
+    assert_eq!(
+        report.content,
+        "fn main() {\n    helper();\n}\nfn helper() {\n\tprintln!(\"help\");  \n}\n\n"
+    );
+    assert_eq!(report.blocks[0].count(), 2);
+}
+
+#[test]
+fn test_moved_text_is_byte_identical_with_fuzzy_source() {
+    // The SEARCH content differs in whitespace, the original bytes are moved
+    let diff = "------- SEARCH MOVE APPEND
+fn helper() {
+    println!(\"help\");
+}
+=======
++++++++ REPLACE";
+    let result = construct_new_file_content_v2(diff, ORIGINAL, true).unwrap();
+    assert_eq!(
+        result,
+        "\nfn main() {\n    helper();\n}\nfn helper() {\n\tprintln!(\"help\");  \n}\n"
+    );
+}
+
+#[test]
+fn test_move_before_earlier_anchor() {
+    let diff = "------- SEARCH MOVE BEFORE
+fn main() {
+    helper();
+}
+=======
+fn helper() {
++++++++ REPLACE";
+    let result = construct_new_file_content_v2(diff, ORIGINAL, true).unwrap();
+    assert_eq!(
+        result,
+        "fn main() {\n    helper();\n}\nfn helper() {\n\tprintln!(\"help\");  \n}\n\n"
+    );
+}
+
+#[test]
+fn test_move_combines_with_other_blocks() {
+    let diff = "------- SEARCH MOVE PREPEND
+fn main() {
+    helper();
+}
+=======
++++++++ REPLACE
+------- SEARCH
+fn helper() {
+=======
+fn helper_fn() {
++++++++ REPLACE";
+    let expected =
+        "fn main() {\n    helper();\n}\nfn helper_fn() {\n\tprintln!(\"help\");  \n}\n\n";
+    assert_eq!(
+        construct_new_file_content_v2(diff, ORIGINAL, true).unwrap(),
+        expected
+    );
+
+    let diff = "------- SEARCH
+fn helper() {
+=======
+fn helper_fn() {
++++++++ REPLACE
+------- SEARCH MOVE PREPEND
+fn main() {
+    helper();
+}
+=======
++++++++ REPLACE";
+    assert_eq!(
+        construct_new_file_content_v2(diff, ORIGINAL, true).unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn test_missing_anchor_fails() {
+    let missing_anchor = "------- SEARCH MOVE AFTER
+fn helper() {
+=======
+fn nowhere() {
++++++++ REPLACE";
+    assert!(matches!(
+        construct_new_file_content_v2(missing_anchor, ORIGINAL, true),
+        Err(DiffError::SearchBlockNotFound(ref anchor)) if anchor == "fn nowhere() {"
+    ));
+}
+
+#[test]
+fn test_anchor_is_not_looked_up_inside_moved_text() {
+    let inside = "------- SEARCH MOVE AFTER
+fn helper() {
+\tprintln!(\"help\");  
+}
+=======
+}
++++++++ REPLACE";
+    let result = construct_new_file_content_v2(inside, ORIGINAL, true).unwrap();
+    assert_eq!(
+        result,
+        "\nfn main() {\n    helper();\n}\nfn helper() {\n\tprintln!(\"help\");  \n}\n"
+    );
+}
+
+#[test]
+fn test_streaming_move_shows_nothing_until_complete() {
+    let diff = "------- SEARCH MOVE AFTER\nfn helper() {\n\tprintln!(\"help\");  \n}\n=======\nfn main() {";
+    assert_eq!(
+        construct_new_file_content_v2(diff, ORIGINAL, false).unwrap(),
+        ""
+    );
+}
+
+#[test]
+fn test_invalid_move_blocks() {
+    for diff in [
+        "------- SEARCH MOVE\nfn helper() {\n=======\nfn main() {\n+++++++ REPLACE",
+        "------- SEARCH MOVE AFTER\n=======\nfn main() {\n+++++++ REPLACE",
+        "------- SEARCH MOVE AFTER\nfn helper() {\n=======\n+++++++ REPLACE",
+        "------- SEARCH MOVE APPEND\nfn helper() {\n=======\nfn main() {\n+++++++ REPLACE",
+        "------- SEARCH MOVE AFTER ALL\n}\n=======\nfn main() {\n+++++++ REPLACE",
+    ] {
+        assert!(
+            matches!(
+                construct_new_file_content_v2(diff, ORIGINAL, true),
+                Err(DiffError::InvalidBlockModifiers(_))
+            ),
+            "{diff}"
+        );
+    }
+}
@@ -0,0 +1,302 @@
+use proptest::prelude::*;
+use replace_in_file::{
+    BlockKind, BlockModifiers, DiffError, DiffOptions, EditBlock, MarkerSet, Occurrence,
+    StrReplaceRequest, apply_edit_blocks, compose_diffs, construct_new_file_content_v1,
+    construct_new_file_content_v1_bytes, construct_new_file_content_v1_with_options,
+    construct_new_file_content_v2, construct_new_file_content_v2_bytes,
+    construct_new_file_content_v2_with_options, construct_new_file_content_xml, decode_text,
+    detect_truncation, encode_text, rebase_diff, search_replace_equivalent, str_replace,
+};
+
+// Arbitrary diffs and files must produce a result or an error, never a panic
+
+/// Content lines from a small pool, so SEARCH sections often (fuzzily) match the file,
+/// with multibyte characters next to the whitespace that fuzzy matching trims and regex
+/// patterns spanning lines
+fn content_line() -> impl Strategy<Value = String> {
+    prop_oneof![
+        Just(String::new()),
+        Just("a".to_string()),
+        Just("  a".to_string()),
+        Just("b\t".to_string()),
+        Just("é".to_string()),
+        Just(" é ".to_string()),
+        Just("€😀".to_string()),
+        Just("(a.*)$".to_string()),
+        Just("bc".to_string()),
+        Just("c".to_string()),
+        Just(r"a\nb".to_string()),
+        Just(r"\n*b".to_string()),
+        Just("...".to_string()),
+        Just("// ... existing code ...".to_string()),
+        "[ \t]{0,2}[aé€😀b.$]{0,4}[ \t]{0,2}",
+    ]
+}
+
+/// Lines that are likely to hit marker parsing and the repair of malformed blocks
+fn diff_line() -> impl Strategy<Value = String> {
+    let markers = prop::sample::select(vec![
+        "------- SEARCH",
+        "------- SEARCH ALL",
+        "------- SEARCH #2 FILE",
+        "------- SEARCH @L2",
+        "------- SEARCH REGEX",
+        "------- SEARCH REGEX MULTILINE",
+        "------- SEARCH REGEX MAX=1",
+        "------- SEARCH BEFORE",
+        "------- SEARCH AFTER",
+        "------- SEARCH PREPEND",
+        "------- SEARCH APPEND",
+        "------- SEARCH MOVE AFTER",
+        "------- SEARCH MOVE APPEND",
+        "<<<<<<< SEARCH",
+        "=======",
+        "+++++++ REPLACE",
+        ">>>>>>> REPLACE",
+        "-------",
+        "+++",
+        "\\=======",
+    ])
+    .prop_map(str::to_string);
+    prop_oneof![markers, content_line()]
+}
+
+/// Regex patterns whose matches span lines, so they end inside the line of a later anchor
+fn spanning_regex() -> impl Strategy<Value = String> {
+    prop::sample::select(vec![r"a\nb", r"\n*b", r"a\n", r"\nbc?", r"(?s)a.b"])
+        .prop_map(str::to_string)
+}
+
+fn lines(line: impl Strategy<Value = String>, max: usize) -> impl Strategy<Value = String> {
+    (prop::collection::vec(line, 0..max), any::<bool>()).prop_map(|(lines, newline)| {
+        let mut text = lines.join("\n");
+        if newline {
+            text.push('\n');
+        }
+        text
+    })
+}
+
+/// A well-formed block, so matching and rendering are exercised and not only parsing
+fn block() -> impl Strategy<Value = String> {
+    (
+        diff_line(),
+        lines(content_line(), 4),
+        lines(content_line(), 3),
+    )
+        .prop_map(|(start, search, replace)| {
+            format!("{start}\n{search}\n=======\n{replace}\n+++++++ REPLACE")
+        })
+}
+
+fn diff() -> impl Strategy<Value = String> {
+    let part = prop_oneof![3 => block(), 1 => diff_line()];
+    prop::collection::vec(part, 0..5).prop_map(|parts| parts.join("\n"))
+}
+
+fn original() -> impl Strategy<Value = String> {
+    prop_oneof![lines(content_line(), 8), ".{0,20}"]
+}
+
+/// Modifiers as code may build them, including values the marker syntax rejects
+fn modifiers() -> impl Strategy<Value = BlockModifiers> {
+    let kind = prop::sample::select(vec![
+        BlockKind::Replace,
+        BlockKind::InsertBefore,
+        BlockKind::InsertAfter,
+        BlockKind::Prepend,
+        BlockKind::Append,
+    ]);
+    let occurrence = prop_oneof![
+        Just(Occurrence::First),
+        Just(Occurrence::All),
+        (0..3usize).prop_map(Occurrence::Nth),
+    ];
+    (
+        kind,
+        prop::option::of(0..4usize),
+        occurrence,
+        any::<[bool; 4]>(),
+        prop::option::of(0..3usize),
+    )
+        .prop_map(
+            |(kind, line_hint, occurrence, [whole_file, regex, multiline, move_text], max)| {
+                BlockModifiers {
+                    kind,
+                    line_hint,
+                    occurrence,
+                    whole_file,
+                    regex,
+                    multiline,
+                    max_replacements: max,
+                    move_text,
+                }
+            },
+        )
+}
+
+fn edit_block() -> impl Strategy<Value = EditBlock> {
+    (
+        lines(content_line(), 3),
+        lines(content_line(), 3),
+        modifiers(),
+    )
+        .prop_map(|(search, replace, modifiers)| EditBlock {
+            search,
+            replace,
+            modifiers,
+        })
+}
+
+proptest! {
+    #[test]
+    fn diff_engines_never_panic(diff in diff(), original in original(), is_final in any::<bool>()) {
+        let _ = construct_new_file_content_v1(&diff, &original, is_final);
+        let merge = DiffOptions { merge_duplicate_blocks: true, ..Default::default() };
+        let _ = construct_new_file_content_v1_with_options(&diff, &original, is_final, &merge);
+        let _ = construct_new_file_content_v2(&diff, &original, is_final);
+        let xml = DiffOptions { markers: MarkerSet::xml_tags("search", "replace"), ..Default::default() };
+        let _ = construct_new_file_content_v2_with_options(&diff, &original, is_final, &xml);
+    }
+
+    #[test]
+    fn insertions_next_to_regex_blocks_never_panic(
+        pattern in spanning_regex(),
+        kind in prop::sample::select(vec!["BEFORE", "AFTER", "MOVE BEFORE", "MOVE AFTER"]),
+        original in lines(content_line(), 8),
+        (line, split) in (any::<prop::sample::Index>(), any::<prop::sample::Index>()),
+        replace in lines(content_line(), 3),
+        regex_first in any::<bool>(),
+    ) {
+        // The anchor is the end of a line of the file, often right after a regex match
+        let file_lines: Vec<&str> = original.lines().collect();
+        let anchor = if file_lines.is_empty() {
+            ""
+        } else {
+            let line = line.get(&file_lines);
+            let starts: Vec<usize> = line.char_indices().map(|(i, _)| i).collect();
+            starts.get(split.index(starts.len().max(1))).map_or("", |&i| &line[i..])
+        };
+        let regex = format!("------- SEARCH REGEX\n{pattern}\n=======\nZ\n+++++++ REPLACE");
+        let insertion = format!("------- SEARCH {kind}\n{anchor}\n=======\n{replace}\n+++++++ REPLACE");
+        let diff = if regex_first {
+            format!("{regex}\n{insertion}")
+        } else {
+            format!("{insertion}\n{regex}")
+        };
+        let _ = construct_new_file_content_v2(&diff, &original, true);
+    }
+
+    #[test]
+    fn byte_variants_never_panic(
+        diff in diff(),
+        original in prop::collection::vec(any::<u8>(), 0..40),
+        is_final in any::<bool>(),
+    ) {
+        let _ = construct_new_file_content_v1_bytes(&diff, &original, is_final);
+        let _ = construct_new_file_content_v2_bytes(&diff, &original, is_final);
+    }
+
+    #[test]
+    fn decoded_content_encodes_back_to_the_same_bytes(content in prop::collection::vec(any::<u8>(), 0..40)) {
+        if let Ok((text, encoding)) = decode_text(&content) {
+            prop_assert_eq!(encode_text(&text, encoding).ok(), Some(content));
+        }
+    }
+
+    #[test]
+    fn streaming_prefixes_never_panic(diff in diff(), original in original()) {
+        for (index, _) in diff.char_indices() {
+            let _ = construct_new_file_content_v2(&diff[..index], &original, false);
+        }
+    }
+
+    #[test]
+    fn rebase_never_panics(diff in diff(), base in original(), current in original()) {
+        let _ = rebase_diff(&diff, &base, &current, &DiffOptions::default());
+    }
+
+    #[test]
+    fn compose_never_panics(first in diff(), second in diff(), original in original()) {
+        let _ = compose_diffs(&first, &second, &original, &DiffOptions::default());
+    }
+
+    #[test]
+    fn edit_blocks_never_panic(
+        blocks in prop::collection::vec(edit_block(), 0..4),
+        original in original(),
+        is_final in any::<bool>(),
+    ) {
+        let _ = apply_edit_blocks(&blocks, &original, is_final, &DiffOptions::default());
+    }
+
+    #[test]
+    fn other_entry_points_never_panic(
+        old_str in lines(content_line(), 4),
+        new_str in lines(content_line(), 4),
+        original in original(),
+        replace_all in any::<bool>(),
+    ) {
+        let request = StrReplaceRequest { path: String::new(), old_str, new_str, replace_all };
+        let _ = str_replace(&original, &request);
+        let xml = format!("<edit><old>{}</old><new>{}</new></edit>", request.old_str, request.new_str);
+        let _ = construct_new_file_content_xml(&xml, &original, true);
+        let _ = detect_truncation(&request.new_str, Some(&original));
+
+        let _ = search_replace_equivalent(&original, &request.new_str);
+    }
+}
+
+#[test]
+fn test_search_longer_than_file_is_not_found() {
+    let diff = "------- SEARCH\n a\nb\n=======\nx\n+++++++ REPLACE";
+    assert!(construct_new_file_content_v2(diff, "a", true).is_err());
+    assert!(construct_new_file_content_v1(diff, "a", true).is_err());
+
+    let anchored = "------- SEARCH\n a\nq\nb\n=======\nx\n+++++++ REPLACE";
+    assert!(construct_new_file_content_v2(anchored, "a\nb", true).is_err());
+    assert!(construct_new_file_content_v1(anchored, "a\nb", true).is_err());
+}
+
+#[test]
+fn test_fuzzy_match_of_last_multibyte_line() {
+    // The trimmed match of a last line without a line break used to end past the file
+    let diff = "------- SEARCH\né\n=======\nx\n+++++++ REPLACE\n------- SEARCH\né\n=======\ny\n+++++++ REPLACE";
+    assert!(construct_new_file_content_v2(diff, " é", true).is_err());
+    assert_eq!(
+        construct_new_file_content_v2("------- SEARCH\né\n=======\nx\n+++++++ REPLACE", " é", true)
+            .unwrap(),
+        "x\n"
+    );
+}
+
+#[test]
+fn test_insertion_point_inside_a_regex_replacement() {
+    // A BEFORE block's line start used to fall inside the text a regex block replaced
+    let diff = "------- SEARCH REGEX\na\\nb\n=======\nZ\n+++++++ REPLACE\n------- SEARCH BEFORE\nc\n=======\nINS\n+++++++ REPLACE";
+    assert!(construct_new_file_content_v2(diff, "xa\nbc\n", true).is_err());
+}
+
+#[test]
+fn test_zero_counts_in_code_built_modifiers_are_rejected() {
+    let block = |modifiers| EditBlock {
+        search: "a\n".to_string(),
+        replace: "b\n".to_string(),
+        modifiers,
+    };
+    for modifiers in [
+        BlockModifiers {
+            occurrence: Occurrence::Nth(0),
+            ..Default::default()
+        },
+        BlockModifiers {
+            max_replacements: Some(0),
+            ..Default::default()
+        },
+    ] {
+        assert!(matches!(
+            apply_edit_blocks(&[block(modifiers)], "a\n", true, &DiffOptions::default()),
+            Err(DiffError::InvalidBlockModifiers(_))
+        ));
+    }
+}
@@ -0,0 +1,176 @@
+use replace_in_file::{
+    BlockModifiers, DiffError, DiffOptions, DiffReport, MatchStrategy, Occurrence,
+    construct_new_file_content_v2, construct_new_file_content_v2_with_report,
+    parse_search_replace_blocks,
+};
+
+// ALL / #n / FILE modifiers in the SEARCH start marker
+
+const ORIGINAL: &str =
+    "fn a() {\n    log(x);\n}\nfn b() {\n    log(x);\n}\nfn c() {\n    log(x);\n    log(x);\n}\n";
+
+fn apply_with_report(diff: &str, original: &str) -> DiffReport {
+    construct_new_file_content_v2_with_report(diff, original, true, &DiffOptions::default())
+        .unwrap()
+}
+
+#[test]
+fn test_modifiers_are_parsed() {
+    let blocks = parse_search_replace_blocks(
+        "------- SEARCH ALL FILE\na\n=======\nb\n+++++++ REPLACE\n------- SEARCH #2 @L7\na\n=======\nb\n+++++++ REPLACE",
+        &DiffOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        blocks[0].modifiers,
+        BlockModifiers {
+            occurrence: Occurrence::All,
+            whole_file: true,
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        blocks[1].modifiers,
+        BlockModifiers {
+            line_hint: Some(7),
+            occurrence: Occurrence::Nth(2),
+            ..Default::default()
+        }
+    );
+
+    // `#0` is not a valid occurrence, so the line is content
+    let blocks = parse_search_replace_blocks(
+        "------- SEARCH\n------- SEARCH #0\n=======\n+++++++ REPLACE",
+        &DiffOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(blocks[0].search, "------- SEARCH #0\n");
+}
+
+#[test]
+fn test_replace_all_reports_count() {
+    let report = apply_with_report(
+        "------- SEARCH ALL\n    log(x);\n=======\n    trace(x);\n+++++++ REPLACE",
+        ORIGINAL,
+    );
+    assert_eq!(report.content, ORIGINAL.replace("log", "trace"));
+    assert_eq!(report.blocks.len(), 1);
+    assert_eq!(report.blocks[0].count(), 4);
+    assert_eq!(report.blocks[0].strategy, Some(MatchStrategy::Exact));
+    assert_eq!(report.blocks[0].ranges[0], 9..21);
+}
+
+#[test]
+fn test_replace_all_with_fuzzy_matching() {
+    let report = apply_with_report(
+        "------- SEARCH ALL\n    log(x); \n=======\n    trace(x);\n+++++++ REPLACE",
+        &ORIGINAL.replace("    log", "  log"),
+    );
+    assert_eq!(report.content, ORIGINAL.replace("log", "trace"));
+    assert_eq!(report.blocks[0].count(), 4);
+    assert_eq!(report.blocks[0].strategy, Some(MatchStrategy::LineTrimmed));
+}
+
+#[test]
+fn test_nth_occurrence() {
+    let result = construct_new_file_content_v2(
+        "------- SEARCH #3\n    log(x);\n=======\n    trace(x);\n+++++++ REPLACE",
+        ORIGINAL,
+        true,
+    )
+    .unwrap();
+    assert_eq!(
+        result,
+        "fn a() {\n    log(x);\n}\nfn b() {\n    log(x);\n}\nfn c() {\n    trace(x);\n    log(x);\n}\n"
+    );
+
+    let result = construct_new_file_content_v2(
+        "------- SEARCH #5\n    log(x);\n=======\n    trace(x);\n+++++++ REPLACE",
+        ORIGINAL,
+        true,
+    );
+    assert!(matches!(
+        result,
+        Err(DiffError::OccurrenceNotFound {
+            occurrence: 5,
+            found: 4,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_occurrences_are_counted_after_previous_block() {
+    let diff = "------- SEARCH
+fn b() {
+=======
+fn bee() {
++++++++ REPLACE
+------- SEARCH ALL
+    log(x);
+=======
+    trace(x);
++++++++ REPLACE";
+    let report = apply_with_report(diff, ORIGINAL);
+    assert_eq!(
+        report.content,
+        "fn a() {\n    log(x);\n}\nfn bee() {\n    trace(x);\n}\nfn c() {\n    trace(x);\n    trace(x);\n}\n"
+    );
+    assert_eq!(report.blocks[1].count(), 3);
+}
+
+#[test]
+fn test_whole_file_scope_skips_replaced_text() {
+    let diff = "------- SEARCH
+fn b() {
+    log(x);
+=======
+fn b() {
+    log(x, 1);
++++++++ REPLACE
+------- SEARCH ALL FILE
+    log(x);
+=======
+    trace(x);
++++++++ REPLACE
+------- SEARCH
+fn c() {
+=======
+fn see() {
++++++++ REPLACE";
+    let report = apply_with_report(diff, ORIGINAL);
+    assert_eq!(
+        report.content,
+        "fn a() {\n    trace(x);\n}\nfn b() {\n    log(x, 1);\n}\nfn see() {\n    trace(x);\n    trace(x);\n}\n"
+    );
+    assert_eq!(report.blocks[1].count(), 3);
+}
+
+#[test]
+fn test_later_block_overlapping_whole_file_replacement_fails() {
+    let diff = "------- SEARCH ALL FILE
+    log(x);
+=======
+    trace(x);
++++++++ REPLACE
+------- SEARCH
+fn c() {
+    log(x);
+=======
++++++++ REPLACE";
+    let result = construct_new_file_content_v2(diff, ORIGINAL, true);
+    assert!(matches!(
+        result,
+        Err(DiffError::SearchBlockIncorrectMatch(_))
+    ));
+}
+
+#[test]
+fn test_streaming_replace_all_preview() {
+    let diff = "------- SEARCH ALL\n    log(x);\n=======\n    trace(x);";
+    let preview = construct_new_file_content_v2(diff, ORIGINAL, false).unwrap();
+    assert_eq!(
+        preview,
+        "fn a() {\n    trace(x);\n}\nfn b() {\n    trace(x);\n}\nfn c() {\n    trace(x);\n    trace(x);\n"
+    );
+}
@@ -0,0 +1,118 @@
+use replace_in_file::{
+    DiffError, DiffOptions, construct_new_file_content_v2,
+    construct_new_file_content_v2_with_report,
+};
+
+// Blocks listed out of file order in the v2 engine
+
+const ORIGINAL: &str = "alpha\nbeta\ngamma\ndelta\n";
+
+#[test]
+fn test_report_keeps_diff_order() {
+    let diff = "------- SEARCH
+gamma
+=======
+GAMMA
++++++++ REPLACE
+------- SEARCH
+alpha
+=======
+ALPHA
++++++++ REPLACE";
+    let report =
+        construct_new_file_content_v2_with_report(diff, ORIGINAL, true, &DiffOptions::default())
+            .unwrap();
+    assert_eq!(report.content, "ALPHA\nbeta\nGAMMA\ndelta\n");
+    assert_eq!(report.blocks[0].ranges, vec![11..17]);
+    assert_eq!(report.blocks[1].ranges, vec![0..6]);
+}
+
+#[test]
+fn test_streaming_preview_of_out_of_order_block() {
+    let diff = "------- SEARCH
+gamma
+=======
+GAMMA
++++++++ REPLACE
+------- SEARCH
+alpha
+=======
+AL";
+    // The preview still reaches the end of the last in-order block
+    assert_eq!(
+        construct_new_file_content_v2(diff, ORIGINAL, false).unwrap(),
+        "AL\nbeta\nGAMMA\n"
+    );
+}
+
+#[test]
+fn test_block_overlapping_earlier_replacement_fails() {
+    let diff = "------- SEARCH
+beta
+gamma
+=======
+BETA GAMMA
++++++++ REPLACE
+------- SEARCH
+alpha
+beta
+=======
++++++++ REPLACE";
+    assert!(matches!(
+        construct_new_file_content_v2(diff, ORIGINAL, true),
+        Err(DiffError::SearchBlockNotFound(_))
+    ));
+}
+
+#[test]
+fn test_later_occurrence_is_preferred_over_earlier_one() {
+    let original = "x = 1\nsep\nx = 1\nend\n";
+    let diff = "------- SEARCH
+sep
+=======
+SEP
++++++++ REPLACE
+------- SEARCH
+x = 1
+=======
+x = 2
++++++++ REPLACE";
+    assert_eq!(
+        construct_new_file_content_v2(diff, original, true).unwrap(),
+        "x = 1\nSEP\nx = 2\nend\n"
+    );
+
+    // Only when nothing follows the previous block, the earlier text is used
+    let diff = "------- SEARCH
+end
+=======
+END
++++++++ REPLACE
+------- SEARCH
+x = 1
+=======
+x = 2
++++++++ REPLACE";
+    assert_eq!(
+        construct_new_file_content_v2(diff, original, true).unwrap(),
+        "x = 2\nsep\nx = 1\nEND\n"
+    );
+}
+
+#[test]
+fn test_out_of_order_insertion() {
+    let diff = "------- SEARCH
+delta
+=======
+DELTA
++++++++ REPLACE
+------- SEARCH AFTER
+alpha
+=======
+alpha2
++++++++ REPLACE";
+    assert_eq!(
+        construct_new_file_content_v2(diff, ORIGINAL, true).unwrap(),
+        "alpha\nalpha2\nbeta\ngamma\nDELTA\n"
+    );
+}
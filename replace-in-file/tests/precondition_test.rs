@@ -0,0 +1,111 @@
+use replace_in_file::{
+    DiffError, DiffOptions, EditorCommand, EditorTool, Precondition, apply_to_path,
+    construct_new_file_content_v1_with_options, construct_new_file_content_v2_with_options,
+    content_sha256,
+};
+use std::fs;
+use std::time::{Duration, UNIX_EPOCH};
+
+// Refusing diffs written against content that has changed since
+
+const ORIGINAL: &str = "abc";
+
+const DIFF: &str = "------- SEARCH
+abc
+=======
+xyz
++++++++ REPLACE";
+
+fn expecting(precondition: Precondition) -> DiffOptions {
+    DiffOptions {
+        expected_original: Some(precondition),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_content_sha256() {
+    assert_eq!(
+        content_sha256(ORIGINAL),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+}
+
+#[test]
+fn test_matching_hash_applies() {
+    // Hashes are compared regardless of case
+    let options = expecting(Precondition::Sha256(
+        content_sha256(ORIGINAL).to_uppercase(),
+    ));
+    assert_eq!(
+        construct_new_file_content_v2_with_options(DIFF, ORIGINAL, true, &options).unwrap(),
+        "xyz\n"
+    );
+    assert_eq!(
+        construct_new_file_content_v1_with_options(DIFF, ORIGINAL, true, &options).unwrap(),
+        "xyz\n"
+    );
+}
+
+#[test]
+fn test_stale_hash_reports_both_hashes() {
+    let options = expecting(Precondition::Sha256(content_sha256("abd")));
+    for result in [
+        construct_new_file_content_v2_with_options(DIFF, ORIGINAL, true, &options),
+        construct_new_file_content_v1_with_options(DIFF, ORIGINAL, true, &options),
+    ] {
+        let Err(DiffError::StaleOriginal { expected, actual }) = result else {
+            panic!("expected a stale original, got {result:?}");
+        };
+        assert_eq!(expected, format!("sha256 {}", content_sha256("abd")));
+        assert_eq!(actual, format!("sha256 {}", content_sha256(ORIGINAL)));
+    }
+}
+
+#[test]
+fn test_file_modified_since_read() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a.txt");
+    fs::write(&path, ORIGINAL).unwrap();
+    let read = Precondition::modified_of(&fs::metadata(&path).unwrap()).unwrap();
+
+    // The user edits the file in the meantime
+    fs::write(&path, "abc\nabc\n").unwrap();
+    let result = apply_to_path(&path, DIFF, &expecting(read.clone()));
+    assert!(matches!(result, Err(DiffError::StaleOriginal { .. })));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "abc\nabc\n");
+
+    let stale = Precondition::Modified {
+        mtime: UNIX_EPOCH + Duration::from_secs(1),
+        size: 3,
+    };
+    assert_eq!(stale.to_string(), "modified at 1.000000000s, 3 bytes");
+
+    let current = Precondition::modified_of(&fs::metadata(&path).unwrap()).unwrap();
+    let outcome = apply_to_path(&path, DIFF, &expecting(current)).unwrap();
+    assert_eq!(outcome.content, "xyz\nabc\n");
+}
+
+#[test]
+fn test_editor_tool_expected_hash() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a.txt");
+    fs::write(&path, ORIGINAL).unwrap();
+    let tool = &mut EditorTool::new();
+    let command = |hash: String| {
+        serde_json::from_value::<EditorCommand>(serde_json::json!({
+            "command": "replace_in_file",
+            "path": path,
+            "diff": DIFF,
+            "expected_sha256": hash,
+        }))
+        .unwrap()
+    };
+
+    let stale = tool.execute(&command(content_sha256("old")));
+    assert!(matches!(stale, Err(DiffError::StaleOriginal { .. })));
+    assert_eq!(fs::read_to_string(&path).unwrap(), ORIGINAL);
+
+    tool.execute(&command(content_sha256(ORIGINAL))).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "xyz\n");
+}
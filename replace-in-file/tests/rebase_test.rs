@@ -0,0 +1,222 @@
+use proptest::prelude::*;
+use replace_in_file::{
+    DiffError, DiffOptions, RebaseConflict, construct_new_file_content_v2, rebase_diff,
+};
+
+// Rebasing a diff written against an older version of the file
+
+const BASE: &str = "fn a() {
+    one();
+}
+
+fn b() {
+    two();
+}
+";
+
+const DIFF: &str = "------- SEARCH
+    one();
+=======
+    uno();
++++++++ REPLACE
+------- SEARCH
+    two();
+=======
+    dos();
++++++++ REPLACE";
+
+#[test]
+fn test_unrelated_changes_rebase_cleanly() {
+    let current = "use x;\n\nfn a() {\n    one();\n}\n\nfn extra() {}\n\nfn b() {\n    two();\n}\n";
+    let outcome = rebase_diff(DIFF, BASE, current, &DiffOptions::default()).unwrap();
+    assert!(outcome.is_clean());
+    assert_eq!(
+        outcome.content,
+        "use x;\n\nfn a() {\n    uno();\n}\n\nfn extra() {}\n\nfn b() {\n    dos();\n}\n"
+    );
+}
+
+#[test]
+fn test_changed_region_is_a_conflict() {
+    let current = BASE.replace("    two();\n", "    two(2);\n    three();\n");
+    let outcome = rebase_diff(DIFF, BASE, &current, &DiffOptions::default()).unwrap();
+
+    // The other block still applies and the conflicting region is left as it is
+    assert_eq!(
+        outcome.content,
+        current.replace("    one();\n", "    uno();\n")
+    );
+    let start = current.find("    two(2)").unwrap();
+    let end = start + "    two(2);\n    three();\n".len();
+    assert_eq!(
+        outcome.conflicts,
+        vec![RebaseConflict {
+            block: 1,
+            base: "    two();\n".to_string(),
+            replacement: "    dos();\n".to_string(),
+            current_range: Some(start..end),
+            current: "    two(2);\n    three();\n".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn test_edits_inside_a_replaced_region_are_a_conflict() {
+    let base = "fn f() {\n    let a = 1;\n    let b = 2;\n    a + b\n}\n";
+    let diff = "------- SEARCH
+fn f() {
+    let a = 1;
+    let b = 2;
+    a + b
+}
+=======
+fn f() {
+    let a = 1;
+    let b = 3;
+    a + b
+}
++++++++ REPLACE";
+    let current = base.replace("let a = 1;", "let a = 100; // user edit");
+    let outcome = rebase_diff(diff, base, &current, &DiffOptions::default()).unwrap();
+
+    // A fuzzy match would revert the user's edit
+    assert!(!outcome.is_clean());
+    assert_eq!(outcome.content, current);
+    assert_eq!(outcome.conflicts[0].current, current);
+}
+
+#[test]
+fn test_moved_text_must_be_unique() {
+    let moved = "fn b() {\n    two();\n}\n\nfn a() {\n    one();\n}\n";
+    let outcome = rebase_diff(DIFF, BASE, moved, &DiffOptions::default()).unwrap();
+    assert!(outcome.is_clean());
+    assert_eq!(
+        outcome.content,
+        moved.replace("one", "uno").replace("two", "dos")
+    );
+
+    // With a pasted copy above it, the moved text could be either occurrence
+    let current = format!("fn c() {{\n    two();\n}}\n\n{moved}");
+    let outcome = rebase_diff(DIFF, BASE, &current, &DiffOptions::default()).unwrap();
+    assert_eq!(outcome.content, current.replace("one", "uno"));
+    assert_eq!(outcome.conflicts.len(), 1);
+    assert_eq!(outcome.conflicts[0].block, 1);
+}
+
+#[test]
+fn test_same_change_on_both_sides() {
+    let current = BASE.replace("    two();\n", "    dos();\n");
+    let outcome = rebase_diff(DIFF, BASE, &current, &DiffOptions::default()).unwrap();
+    assert!(outcome.is_clean());
+    assert_eq!(
+        outcome.content,
+        BASE.replace("one", "uno").replace("two", "dos")
+    );
+}
+
+#[test]
+fn test_insertions_follow_their_line() {
+    let diff = "------- SEARCH AFTER
+fn b() {
+=======
+    setup();
++++++++ REPLACE
+------- SEARCH APPEND
+=======
+// end
++++++++ REPLACE";
+    let current = format!("// header\n{BASE}");
+    let outcome = rebase_diff(diff, BASE, &current, &DiffOptions::default()).unwrap();
+    assert!(outcome.is_clean());
+    assert_eq!(
+        outcome.content,
+        format!(
+            "// header\n{}// end\n",
+            BASE.replace("fn b() {\n", "fn b() {\n    setup();\n")
+        )
+    );
+}
+
+#[test]
+fn test_insertions_after_repeated_lines() {
+    let base = "}\nfn a() {\n}\nfn b() {\n}\n";
+    let diff = "------- SEARCH AFTER\nfn b() {\n}\n=======\nfn c() {\n}\n+++++++ REPLACE";
+    let outcome = rebase_diff(diff, base, base, &DiffOptions::default()).unwrap();
+    assert!(outcome.is_clean());
+    assert_eq!(outcome.content, format!("{base}fn c() {{\n}}\n"));
+
+    // An insertion right after a replacement stays right after it
+    let diff = "------- SEARCH\nfn b() {\n=======\nfn b(x) {\n+++++++ REPLACE
+------- SEARCH BEFORE\n}\n=======\n    x;\n+++++++ REPLACE";
+    let current = format!("{base}}}\n");
+    let outcome = rebase_diff(diff, base, &current, &DiffOptions::default()).unwrap();
+    assert!(outcome.is_clean());
+    assert_eq!(outcome.content, "}\nfn a() {\n}\nfn b(x) {\n    x;\n}\n}\n");
+
+    // When the repeated line can't be told apart, the insertion is a conflict
+    let diff = "------- SEARCH AFTER\nfn a() {\n}\n=======\n// a\n+++++++ REPLACE";
+    let current = "}\n}\n";
+    let outcome = rebase_diff(diff, base, current, &DiffOptions::default()).unwrap();
+    assert_eq!(outcome.content, current);
+    assert_eq!(outcome.conflicts.len(), 1);
+}
+
+#[test]
+fn test_diff_must_apply_to_its_base() {
+    let diff = "------- SEARCH\nmissing\n=======\nx\n+++++++ REPLACE";
+    let result = rebase_diff(diff, BASE, BASE, &DiffOptions::default());
+    assert!(matches!(result, Err(DiffError::SearchBlockNotFound(_))));
+}
+
+fn line() -> impl Strategy<Value = &'static str> {
+    prop::sample::select(vec!["}", "fn a() {", "a", "  a", "b", ""])
+}
+
+/// A block with SEARCH content taken from the lines of `content`, so it usually applies
+fn block(content: &str, start: usize, count: usize, marker: &str, replace: &[&str]) -> String {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let search = if lines.is_empty() || marker.ends_with("PREPEND") || marker.ends_with("APPEND") {
+        String::new()
+    } else {
+        let start = start % lines.len();
+        lines[start..(start + count).min(lines.len())].concat()
+    };
+    let replace: String = replace.iter().map(|line| format!("{line}\n")).collect();
+    format!("{marker}\n{search}=======\n{replace}+++++++ REPLACE")
+}
+
+proptest! {
+    #[test]
+    fn rebasing_onto_the_base_applies_the_diff(
+        base in prop::collection::vec(line(), 0..10),
+        blocks in prop::collection::vec(
+            (
+                any::<usize>(),
+                1..3usize,
+                prop::sample::select(vec![
+                    "------- SEARCH",
+                    "------- SEARCH ALL",
+                    "------- SEARCH #2",
+                    "------- SEARCH BEFORE",
+                    "------- SEARCH AFTER",
+                    "------- SEARCH PREPEND",
+                    "------- SEARCH APPEND",
+                ]),
+                prop::collection::vec(line(), 0..3),
+            ),
+            1..4,
+        ),
+    ) {
+        let base: String = base.iter().map(|line| format!("{line}\n")).collect();
+        let diff = blocks
+            .iter()
+            .map(|(start, count, marker, replace)| block(&base, *start, *count, marker, replace))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if let Ok(expected) = construct_new_file_content_v2(&diff, &base, true) {
+            let outcome = rebase_diff(&diff, &base, &base, &DiffOptions::default()).unwrap();
+            prop_assert!(outcome.is_clean(), "{:?}", outcome.conflicts);
+            prop_assert_eq!(outcome.content, expected);
+        }
+    }
+}
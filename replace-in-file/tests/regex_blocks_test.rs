@@ -0,0 +1,121 @@
+use replace_in_file::{
+    DiffError, DiffOptions, MatchStrategy, construct_new_file_content_v2,
+    construct_new_file_content_v2_with_report,
+};
+
+// REGEX blocks: regular expression SEARCH content with capture-group replacements
+
+const ORIGINAL: &str = "let a = get_value(1);\nlet b = get_value(2);\nlet c = get_value(3);\n";
+
+#[test]
+fn test_regex_replace_all_with_captures() {
+    let diff = r"------- SEARCH REGEX ALL
+get_value\((\d+)\)
+=======
+value(${1}u32)
++++++++ REPLACE";
+    let report =
+        construct_new_file_content_v2_with_report(diff, ORIGINAL, true, &DiffOptions::default())
+            .unwrap();
+    assert_eq!(
+        report.content,
+        "let a = value(1u32);\nlet b = value(2u32);\nlet c = value(3u32);\n"
+    );
+    assert_eq!(report.blocks[0].count(), 3);
+    assert_eq!(report.blocks[0].strategy, Some(MatchStrategy::Regex));
+}
+
+#[test]
+fn test_regex_replaces_first_match_by_default() {
+    let diff = r"------- SEARCH REGEX
+let (?<name>\w) =
+=======
+let mut $name =
++++++++ REPLACE";
+    let result = construct_new_file_content_v2(diff, ORIGINAL, true).unwrap();
+    assert_eq!(
+        result,
+        "let mut a = get_value(1);\nlet b = get_value(2);\nlet c = get_value(3);\n"
+    );
+}
+
+#[test]
+fn test_regex_replacement_cap() {
+    let diff = r"------- SEARCH REGEX ALL MAX=2
+get_value
+=======
+fetch
++++++++ REPLACE";
+    let report =
+        construct_new_file_content_v2_with_report(diff, ORIGINAL, true, &DiffOptions::default())
+            .unwrap();
+    assert_eq!(
+        report.content,
+        "let a = fetch(1);\nlet b = fetch(2);\nlet c = get_value(3);\n"
+    );
+    assert_eq!(report.blocks[0].count(), 2);
+}
+
+#[test]
+fn test_regex_multiline_anchors() {
+    let diff = r"------- SEARCH REGEX ALL MULTILINE
+^let
+=======
+const
++++++++ REPLACE";
+    let result = construct_new_file_content_v2(diff, ORIGINAL, true).unwrap();
+    assert_eq!(result, ORIGINAL.replace("let", "const"));
+
+    // Without the flag `^` only matches at the start of the file
+    let diff = diff.replace(" MULTILINE", "");
+    let result = construct_new_file_content_v2(&diff, ORIGINAL, true).unwrap();
+    assert_eq!(result, ORIGINAL.replacen("let", "const", 1));
+}
+
+#[test]
+fn test_regex_respects_previous_blocks() {
+    let diff = r"------- SEARCH
+let b = get_value(2);
+=======
+let b = 2;
++++++++ REPLACE
+------- SEARCH REGEX ALL
+get_value\((\d)\)
+=======
+$1
++++++++ REPLACE";
+    let result = construct_new_file_content_v2(diff, ORIGINAL, true).unwrap();
+    assert_eq!(result, "let a = get_value(1);\nlet b = 2;\nlet c = 3;\n");
+}
+
+#[test]
+fn test_regex_matches_from_the_previous_block() {
+    // From the start of the file, the leftmost match would begin inside the first block
+    let diff = r"------- SEARCH
+a
+=======
+A
++++++++ REPLACE
+------- SEARCH REGEX
+\n*b
+=======
+B
++++++++ REPLACE";
+    let result = construct_new_file_content_v2(diff, "a\n\nb\n", true).unwrap();
+    assert_eq!(result, "A\nB\n");
+}
+
+#[test]
+fn test_regex_errors() {
+    let invalid = "------- SEARCH REGEX\nget_value(\n=======\nx\n+++++++ REPLACE";
+    assert!(matches!(
+        construct_new_file_content_v2(invalid, ORIGINAL, true),
+        Err(DiffError::InvalidRegex(_))
+    ));
+
+    let missing = "------- SEARCH REGEX\nset_\\w+\n=======\nx\n+++++++ REPLACE";
+    assert!(matches!(
+        construct_new_file_content_v2(missing, ORIGINAL, true),
+        Err(DiffError::SearchBlockNotFound(_))
+    ));
+}
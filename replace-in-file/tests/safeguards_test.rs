@@ -0,0 +1,116 @@
+use replace_in_file::{
+    DiffError, DiffOptions, apply_to_path, construct_new_file_content_v1,
+    construct_new_file_content_v2, construct_new_file_content_v2_bytes,
+    construct_new_file_content_v2_with_options,
+};
+use std::fs;
+
+// Binary files are refused and minified files only match exactly
+
+const DIFF: &str = "------- SEARCH
+b = 2
+=======
+b = 3
++++++++ REPLACE";
+
+fn minified(statements: usize) -> String {
+    let mut content: String = (0..statements).map(|i| format!("var v{i}={i};")).collect();
+    content.push('\n');
+    content
+}
+
+#[test]
+fn test_binary_content_is_refused() {
+    let original = "a = 1\nb = 2\0\u{1}\n";
+    assert!(matches!(
+        construct_new_file_content_v2(DIFF, original, true),
+        Err(DiffError::BinaryContent)
+    ));
+    assert!(matches!(
+        construct_new_file_content_v1(DIFF, original, true),
+        Err(DiffError::BinaryContent)
+    ));
+    assert!(matches!(
+        construct_new_file_content_v2_bytes(DIFF, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", true),
+        Err(DiffError::BinaryContent)
+    ));
+}
+
+#[test]
+fn test_binary_file_is_left_untouched() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data.bin");
+    fs::write(&path, b"b = 2\n\0\xff\n").unwrap();
+
+    let result = apply_to_path(&path, DIFF, &DiffOptions::default());
+    assert!(matches!(result, Err(DiffError::BinaryContent)));
+    assert_eq!(fs::read(&path).unwrap(), b"b = 2\n\0\xff\n");
+}
+
+#[test]
+fn test_minified_file_matches_exactly() {
+    let original = minified(1_000);
+    let diff = "------- SEARCH
+var v999=999;
+=======
+var v999=-1;
++++++++ REPLACE";
+    let expected = original.replace("var v999=999;", "var v999=-1;");
+    assert_eq!(
+        construct_new_file_content_v2(diff, &original, true).unwrap(),
+        expected
+    );
+    assert_eq!(
+        construct_new_file_content_v1(diff, &original, true).unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn test_minified_file_refuses_fuzzy_matches() {
+    // Only a trimmed match would find the whole line
+    let original = minified(1_000);
+    let diff = format!(
+        "------- SEARCH\n  {}\n=======\n\n+++++++ REPLACE",
+        original.trim()
+    );
+
+    for result in [
+        construct_new_file_content_v2(&diff, &original, true),
+        construct_new_file_content_v1(&diff, &original, true),
+    ] {
+        assert!(matches!(
+            result,
+            Err(DiffError::ExactMatchRequired {
+                max_line_length: 5_000,
+                ..
+            })
+        ));
+    }
+}
+
+#[test]
+fn test_max_fuzzy_line_length_option() {
+    let original = "fn main() {\n    run();\n}\n";
+    let diff = "------- SEARCH
+    run();	
+=======
+stop();
++++++++ REPLACE";
+    assert_eq!(
+        construct_new_file_content_v2(diff, original, true).unwrap(),
+        "fn main() {\nstop();\n}\n"
+    );
+
+    let options = DiffOptions {
+        max_fuzzy_line_length: 10,
+        ..Default::default()
+    };
+    assert!(matches!(
+        construct_new_file_content_v2_with_options(diff, original, true, &options),
+        Err(DiffError::ExactMatchRequired {
+            max_line_length: 10,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,172 @@
+use replace_in_file::{
+    DiffError, DiffOptions, Limits, MatchStrategy, Precondition, StrReplaceRequest, content_sha256,
+    str_replace, str_replace_with_options,
+};
+
+// str_replace-style JSON edit API
+
+fn request(old_str: &str, new_str: &str, replace_all: bool) -> StrReplaceRequest {
+    StrReplaceRequest {
+        path: "/repo/src/main.rs".to_string(),
+        old_str: old_str.to_string(),
+        new_str: new_str.to_string(),
+        replace_all,
+    }
+}
+
+#[test]
+fn test_request_deserializes_from_agent_json() {
+    let json =
+        r#"{"path": "/repo/a.txt", "old_str": "foo", "new_str": "bar", "replace_all": true}"#;
+    let parsed: StrReplaceRequest = serde_json::from_str(json).unwrap();
+    assert_eq!(parsed.path, "/repo/a.txt");
+    assert_eq!(parsed.old_str, "foo");
+    assert_eq!(parsed.new_str, "bar");
+    assert!(parsed.replace_all);
+
+    let minimal: StrReplaceRequest =
+        serde_json::from_str(r#"{"path": "/repo/a.txt", "old_str": "foo"}"#).unwrap();
+    assert_eq!(minimal.new_str, "");
+    assert!(!minimal.replace_all);
+}
+
+#[test]
+fn test_unique_substring_is_replaced() {
+    let content = "let count = 1;\nprintln!(\"{}\", total);\n";
+    let outcome = str_replace(content, &request("count = 1", "count = 2", false)).unwrap();
+    assert_eq!(
+        outcome.content,
+        "let count = 2;\nprintln!(\"{}\", total);\n"
+    );
+    assert_eq!(outcome.lines, vec![1]);
+    assert_eq!(outcome.strategy, MatchStrategy::Exact);
+}
+
+#[test]
+fn test_multiple_occurrences_require_replace_all() {
+    let content = "foo();\nbar();\nfoo();\n";
+    let err = str_replace(content, &request("foo()", "baz()", false)).unwrap_err();
+    assert!(matches!(err, DiffError::OldStrNotUnique { ref lines, .. } if *lines == vec![1, 3]));
+    assert_eq!(
+        err.to_string(),
+        "No replacement was performed. Multiple occurrences of old_str `foo()` in lines [1, 3]. Please ensure it is unique."
+    );
+
+    let outcome = str_replace(content, &request("foo()", "baz()", true)).unwrap();
+    assert_eq!(outcome.content, "baz();\nbar();\nbaz();\n");
+    assert_eq!(outcome.lines, vec![1, 3]);
+}
+
+#[test]
+fn test_missing_old_str_error_message() {
+    let err = str_replace("a\nb\n", &request("missing", "x", false)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "No replacement was performed, old_str `missing` did not appear verbatim in /repo/src/main.rs."
+    );
+}
+
+#[test]
+fn test_trimmed_fallback_keeps_line_structure() {
+    let content = "fn main() {\n    let x = 1;\n    let y = 2;\n}\n";
+    let outcome = str_replace(
+        content,
+        &request("let x = 1;\nlet y = 2;", "    let z = 3;", false),
+    )
+    .unwrap();
+    assert_eq!(outcome.content, "fn main() {\n    let z = 3;\n}\n");
+    assert_eq!(outcome.strategy, MatchStrategy::LineTrimmed);
+}
+
+#[test]
+fn test_fuzzy_matches_also_enforce_uniqueness() {
+    let content = "  a\n  b\nc\n    a\n    b\n";
+    let err = str_replace(content, &request("a\nb", "x", false)).unwrap_err();
+    assert!(matches!(err, DiffError::OldStrNotUnique { ref lines, .. } if *lines == vec![1, 4]));
+
+    let outcome = str_replace(content, &request("a\nb", "x", true)).unwrap();
+    assert_eq!(outcome.content, "x\nc\nx\n");
+}
+
+#[test]
+fn test_block_anchor_fallback() {
+    let content = "start\n  stale middle\nend\nrest\n";
+    let outcome = str_replace(
+        content,
+        &request("start\nmiddle\nend\n", "replaced\n", false),
+    )
+    .unwrap();
+    assert_eq!(outcome.content, "replaced\nrest\n");
+    assert_eq!(outcome.strategy, MatchStrategy::BlockAnchor);
+}
+
+#[test]
+fn test_empty_new_str_deletes() {
+    let outcome = str_replace("keep\ndrop me\nkeep\n", &request("drop me\n", "", false)).unwrap();
+    assert_eq!(outcome.content, "keep\nkeep\n");
+}
+
+#[test]
+fn test_invalid_arguments() {
+    assert!(matches!(
+        str_replace("abc", &request("", "x", false)),
+        Err(DiffError::EmptyOldStr)
+    ));
+    assert!(matches!(
+        str_replace("abc", &request("b", "b", false)),
+        Err(DiffError::OldStrEqualsNewStr)
+    ));
+}
+
+#[test]
+fn test_options_apply_to_str_replace() {
+    let content = "one\ntwo\nthree\n";
+    let options = |limits| DiffOptions {
+        limits,
+        ..Default::default()
+    };
+
+    let small_diff = options(Limits {
+        max_diff_bytes: Some(5),
+        ..Default::default()
+    });
+    assert!(matches!(
+        str_replace_with_options(content, &request("two", "deux", false), &small_diff),
+        Err(DiffError::DiffTooLarge { size: 7, limit: 5 })
+    ));
+
+    let deletion = options(Limits {
+        max_deleted_fraction: Some(0.3),
+        ..Default::default()
+    });
+    assert!(matches!(
+        str_replace_with_options(content, &request("one\ntwo\n", "", false), &deletion),
+        Err(DiffError::BlockDeletesTooMuch { .. })
+    ));
+    assert!(str_replace_with_options(content, &request("two\n", "", false), &deletion).is_ok());
+
+    let stale = DiffOptions {
+        expected_original: Some(Precondition::Sha256(content_sha256("one\n"))),
+        ..Default::default()
+    };
+    assert!(matches!(
+        str_replace_with_options(content, &request("two", "deux", false), &stale),
+        Err(DiffError::StaleOriginal { .. })
+    ));
+
+    // Long lines are only matched exactly
+    let exact_only = DiffOptions {
+        max_fuzzy_line_length: 3,
+        ..Default::default()
+    };
+    let trailing = "one  \ntwo\n";
+    let trimmed = request("one\ntwo\n", "1\n2\n", false);
+    assert_eq!(
+        str_replace(trailing, &trimmed).unwrap().strategy,
+        MatchStrategy::LineTrimmed
+    );
+    assert!(matches!(
+        str_replace_with_options(trailing, &trimmed, &exact_only),
+        Err(DiffError::OldStrNotFound { .. })
+    ));
+}
@@ -18,8 +18,9 @@ new second
     let expected = "first\nnew second\nthird\nnew fourth\n";
     assert_eq!(result_v1, expected);
 
-    // v2 is strict and should error for out-of-order
-    assert!(construct_new_file_content_v2(diff, original, is_final).is_err());
+    // v2 resolves out-of-order blocks the same way
+    let result_v2 = construct_new_file_content_v2(diff, original, is_final).unwrap();
+    assert_eq!(result_v2, expected);
 }
 
 #[test]
@@ -45,7 +46,8 @@ fifth
     let expected = "one\nsecond\nthree\nfourth\nfifth\n";
     assert_eq!(result_v1, expected);
 
-    assert!(construct_new_file_content_v2(diff, original, is_final).is_err());
+    let result_v2 = construct_new_file_content_v2(diff, original, is_final).unwrap();
+    assert_eq!(result_v2, expected);
 }
 
 #[test]
@@ -66,7 +68,8 @@ fn v1_out_of_order_with_indentation() {
     let expected = "function test() {\n\tconst a = 10;\n\tconst b = 2;\n\tconst c = 30;\n\n}";
     assert_eq!(result_v1, expected);
 
-    assert!(construct_new_file_content_v2(diff, original, is_final).is_err());
+    let result_v2 = construct_new_file_content_v2(diff, original, is_final).unwrap();
+    assert_eq!(result_v2, expected);
 }
 
 #[test]
@@ -89,7 +92,8 @@ new body content
     let expected = "header\nnew body content\nnew footer\n";
     assert_eq!(result_v1, expected);
 
-    assert!(construct_new_file_content_v2(diff, original, is_final).is_err());
+    let result_v2 = construct_new_file_content_v2(diff, original, is_final).unwrap();
+    assert_eq!(result_v2, expected);
 }
 
 #[test]
@@ -0,0 +1,118 @@
+use replace_in_file::{
+    DiffError, DiffOptions, construct_new_file_content_v1,
+    construct_new_file_content_v1_with_options,
+};
+
+// Overlapping and nested replacement ranges in the v1 engine
+
+#[test]
+fn v1_overlapping_blocks_are_reported() {
+    let original = "one\ntwo\nthree\nfour\n";
+    let diff = "------- SEARCH
+two
+three
+=======
+2 3
++++++++ REPLACE
+------- SEARCH
+one
+two
+=======
+1 2
++++++++ REPLACE";
+    let result = construct_new_file_content_v1(diff, original, true);
+    assert!(matches!(
+        result,
+        Err(DiffError::OverlappingBlocks {
+            first: 0,
+            second: 1
+        })
+    ));
+}
+
+#[test]
+fn v1_nested_blocks_are_reported() {
+    let original = "a\nb\nc\nd\ne\n";
+    let diff = "------- SEARCH
+c
+d
+=======
+C D
++++++++ REPLACE
+------- SEARCH
+e
+=======
+E
++++++++ REPLACE
+------- SEARCH
+b
+c
+d
+=======
+B C D
++++++++ REPLACE";
+    // The third block contains the first one; the unrelated second block between them
+    // doesn't matter
+    let result = construct_new_file_content_v1(diff, original, true);
+    assert!(matches!(
+        result,
+        Err(DiffError::OverlappingBlocks {
+            first: 0,
+            second: 2
+        })
+    ));
+}
+
+#[test]
+fn v1_adjacent_blocks_are_not_overlapping() {
+    let original = "one\ntwo\nthree\n";
+    let diff = "------- SEARCH
+two
+=======
+2
++++++++ REPLACE
+------- SEARCH
+one
+=======
+1
++++++++ REPLACE";
+    let result = construct_new_file_content_v1(diff, original, true).unwrap();
+    assert_eq!(result, "1\n2\nthree\n");
+}
+
+#[test]
+fn v1_duplicate_blocks_can_be_merged() {
+    let original = "one\ntwo\nthree\n";
+    let diff = "------- SEARCH
+two
+=======
+2
++++++++ REPLACE
+------- SEARCH
+two
+=======
+2
++++++++ REPLACE";
+    assert!(matches!(
+        construct_new_file_content_v1(diff, original, true),
+        Err(DiffError::OverlappingBlocks {
+            first: 0,
+            second: 1
+        })
+    ));
+
+    let options = DiffOptions {
+        merge_duplicate_blocks: true,
+        ..Default::default()
+    };
+    let result =
+        construct_new_file_content_v1_with_options(diff, original, true, &options).unwrap();
+    assert_eq!(result, "one\n2\nthree\n");
+
+    // Duplicates with different replacements still conflict
+    let conflicting = diff.replacen("2\n+++++++", "II\n+++++++", 1);
+    assert!(matches!(
+        construct_new_file_content_v1_with_options(&conflicting, original, true, &options),
+        Err(DiffError::OverlappingBlocks { .. })
+    ));
+}
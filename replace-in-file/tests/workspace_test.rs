@@ -0,0 +1,183 @@
+use replace_in_file::{DiffError, DiffOptions, EditorCommand, EditorTool, Workspace};
+use std::fs;
+
+// Confining agent-provided paths to a workspace directory
+
+const DIFF: &str = "------- SEARCH
+a = 1
+=======
+a = 2
++++++++ REPLACE";
+
+fn workspace() -> (tempfile::TempDir, Workspace) {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("root/src")).unwrap();
+    fs::write(dir.path().join("root/src/a.txt"), "a = 1\n").unwrap();
+    let workspace = Workspace::new(dir.path().join("root")).unwrap();
+    (dir, workspace)
+}
+
+#[test]
+fn test_paths_inside_the_root_resolve() {
+    let (_dir, workspace) = workspace();
+    let expected = workspace.root().join("src/a.txt");
+    assert_eq!(workspace.resolve("src/a.txt").unwrap(), expected);
+    assert_eq!(workspace.resolve("./src/../src/a.txt").unwrap(), expected);
+    assert_eq!(workspace.resolve(&expected).unwrap(), expected);
+    // Files to be created don't exist yet
+    assert_eq!(
+        workspace.resolve("new/dir/b.txt").unwrap(),
+        workspace.root().join("new/dir/b.txt")
+    );
+
+    let outcome = workspace
+        .apply_to_path("src/a.txt", DIFF, &DiffOptions::default())
+        .unwrap();
+    assert_eq!(outcome.content, "a = 2\n");
+}
+
+#[test]
+fn test_parent_escapes_are_refused() {
+    let (dir, workspace) = workspace();
+    fs::write(dir.path().join("secret.txt"), "a = 1\n").unwrap();
+
+    for path in [
+        "../secret.txt",
+        "src/../../secret.txt",
+        "new/../../secret.txt",
+    ] {
+        assert!(
+            matches!(
+                workspace.resolve(path),
+                Err(DiffError::PathOutsideWorkspace(_))
+            ),
+            "{path}"
+        );
+    }
+    assert!(matches!(
+        workspace.resolve(dir.path().join("secret.txt")),
+        Err(DiffError::PathOutsideWorkspace(_))
+    ));
+    let result = workspace.apply_to_path("../secret.txt", DIFF, &DiffOptions::default());
+    assert!(matches!(result, Err(DiffError::PathOutsideWorkspace(_))));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("secret.txt")).unwrap(),
+        "a = 1\n"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlinks_are_followed_but_must_stay_inside() {
+    use std::os::unix::fs::symlink;
+
+    let (dir, workspace) = workspace();
+    let root = workspace.root().to_path_buf();
+    fs::create_dir(dir.path().join("outside")).unwrap();
+    symlink(dir.path().join("outside"), root.join("escape")).unwrap();
+    symlink("src", root.join("alias")).unwrap();
+    symlink("../gone.txt", root.join("dangling")).unwrap();
+
+    assert_eq!(
+        workspace.resolve("alias/a.txt").unwrap(),
+        root.join("src/a.txt")
+    );
+    assert!(matches!(
+        workspace.resolve("escape/new.txt"),
+        Err(DiffError::SymlinkOutsideWorkspace { .. })
+    ));
+    assert!(matches!(
+        workspace.resolve("dangling"),
+        Err(DiffError::SymlinkOutsideWorkspace { .. })
+    ));
+    assert!(matches!(
+        workspace.write_to_file("escape/new.txt", "x\n", false),
+        Err(DiffError::SymlinkOutsideWorkspace { .. })
+    ));
+    assert!(!dir.path().join("outside/new.txt").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_dangling_symlinks_are_followed_to_the_end() {
+    use std::os::unix::fs::symlink;
+
+    let (dir, workspace) = workspace();
+    let root = workspace.root().to_path_buf();
+    let outside = dir.path().join("outside");
+    fs::create_dir(&outside).unwrap();
+    // A chain whose last link dangles outside
+    symlink(outside.join("pwned"), root.join("b")).unwrap();
+    symlink("b", root.join("a")).unwrap();
+    // A dangling link through a directory link leading outside
+    symlink(&outside, root.join("out")).unwrap();
+    symlink("out/new.txt", root.join("c")).unwrap();
+    // Dangling links that stay inside still resolve
+    symlink("src/new.txt", root.join("d")).unwrap();
+    symlink("d", root.join("e")).unwrap();
+    symlink("loop", root.join("loop")).unwrap();
+
+    for path in ["a", "b", "c"] {
+        assert!(matches!(
+            workspace.resolve(path),
+            Err(DiffError::SymlinkOutsideWorkspace { .. })
+        ));
+        assert!(workspace.write_to_file(path, "x\n", false).is_err());
+    }
+    assert!(!outside.join("pwned").exists());
+    assert!(!outside.join("new.txt").exists());
+    assert_eq!(workspace.resolve("e").unwrap(), root.join("src/new.txt"));
+    assert!(workspace.resolve("loop").is_err());
+}
+
+#[test]
+fn test_denied_paths() {
+    let (_dir, workspace) = workspace();
+    for (path, pattern) in [
+        (".git/config", ".git/"),
+        ("sub/.git/HEAD", ".git/"),
+        (".env", ".env"),
+        ("app/.env.local", ".env.*"),
+    ] {
+        assert!(
+            matches!(
+                workspace.resolve(path),
+                Err(DiffError::DeniedPath { pattern: ref p, .. }) if p == pattern
+            ),
+            "{path}"
+        );
+    }
+    assert!(workspace.resolve("src/environment.rs").is_ok());
+
+    let workspace = workspace.with_denied_paths(&["secrets/*.key", "*.pem"]);
+    assert!(workspace.resolve(".env").is_ok());
+    assert!(workspace.resolve("secrets/prod.key").is_err());
+    assert!(workspace.resolve("certs/server.pem").is_err());
+    assert!(workspace.resolve("other/secrets/prod.key").is_ok());
+}
+
+#[test]
+fn test_editor_tool_with_workspace() {
+    let (dir, workspace) = workspace();
+    let root = workspace.root().to_path_buf();
+    let tool = &mut EditorTool::with_workspace(workspace);
+
+    tool.execute(&EditorCommand::ReplaceInFile {
+        path: "src/a.txt".to_string(),
+        diff: DIFF.to_string(),
+        expected_sha256: None,
+    })
+    .unwrap();
+    assert_eq!(
+        fs::read_to_string(root.join("src/a.txt")).unwrap(),
+        "a = 2\n"
+    );
+
+    let result = tool.execute(&EditorCommand::Create {
+        path: "../b.txt".to_string(),
+        file_text: "b\n".to_string(),
+        overwrite: false,
+    });
+    assert!(matches!(result, Err(DiffError::PathOutsideWorkspace(_))));
+    assert!(!dir.path().join("b.txt").exists());
+}
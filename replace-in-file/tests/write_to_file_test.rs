@@ -0,0 +1,125 @@
+use replace_in_file::{
+    DiffError, construct_new_file_content_v2, detect_truncation, search_replace_equivalent,
+    write_to_file,
+};
+use std::fs;
+
+// Whole-file writes: truncation checks and the SEARCH/REPLACE equivalent used for logging
+
+#[test]
+fn test_write_creates_parent_directories() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a/b/new.txt");
+
+    let outcome = write_to_file(&path, "hello\n", false).unwrap();
+    assert!(outcome.created);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "hello\n");
+    assert_eq!(
+        outcome.diff,
+        "------- SEARCH\n=======\nhello\n+++++++ REPLACE\n"
+    );
+}
+
+#[test]
+fn test_write_overwrites_existing_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a.txt");
+    fs::write(&path, "one\ntwo\nthree\n").unwrap();
+
+    let outcome = write_to_file(&path, "one\n2\nthree\n", false).unwrap();
+    assert!(!outcome.created);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "one\n2\nthree\n");
+    assert_eq!(
+        outcome.diff,
+        "------- SEARCH\none\ntwo\nthree\n=======\none\n2\nthree\n+++++++ REPLACE\n"
+    );
+}
+
+#[test]
+fn test_truncated_content_is_refused_unless_forced() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("main.rs");
+    fs::write(&path, "fn main() {}\n").unwrap();
+
+    let elided = "fn main() {\n    // ... rest of the code unchanged\n}\n";
+    let result = write_to_file(&path, elided, false);
+    assert!(matches!(
+        result,
+        Err(DiffError::SuspectedTruncation { ref reason, .. }) if reason.contains("line 2")
+    ));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "fn main() {}\n");
+
+    let mid_line = write_to_file(&path, "fn main() {\n    let x = ", false);
+    assert!(matches!(
+        mid_line,
+        Err(DiffError::SuspectedTruncation { .. })
+    ));
+
+    write_to_file(&path, elided, true).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), elided);
+}
+
+#[test]
+fn test_detect_truncation() {
+    assert!(detect_truncation("# existing code here...\nx = 1\n", None).is_some());
+    assert!(detect_truncation("<!-- ... -->\n", None).is_some());
+    assert!(detect_truncation("/* remains unchanged */\n", None).is_some());
+
+    // Bare ellipses are valid Python and TypeScript overload stubs
+    assert!(detect_truncation("def f():\n    ...\n", None).is_none());
+    // A comment that merely mentions existing things is fine
+    assert!(detect_truncation("// check existing users first\n", None).is_none());
+    // Placeholders make up the whole comment, while real comments go on
+    for comment in [
+        "// other functions live in util.rs",
+        "# remaining content is parsed below",
+        " * existing code paths are kept",
+        "// rest of the file handles errors",
+    ] {
+        assert!(detect_truncation(&format!("{comment}\n"), None).is_none());
+    }
+    assert!(detect_truncation("// rest of the file ...\n", None).is_some());
+    // Files that never ended with a newline may keep doing so
+    assert!(detect_truncation("a\nb", Some("a")).is_none());
+    assert!(detect_truncation("a\nb", Some("a\n")).is_some());
+    assert!(detect_truncation("", None).is_none());
+}
+
+#[test]
+fn test_search_replace_equivalent_round_trips() {
+    let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\n";
+    let updated = "a\nb\nc\nd\nE\nf\ng\nh\ni\n";
+    let diff = search_replace_equivalent(original, updated);
+    assert_eq!(
+        diff,
+        "------- SEARCH\nb\nc\nd\ne\nf\ng\nh\n=======\nb\nc\nd\nE\nf\ng\nh\n+++++++ REPLACE\n"
+    );
+    assert_eq!(
+        construct_new_file_content_v2(&diff, original, true).unwrap(),
+        updated
+    );
+    assert_eq!(search_replace_equivalent(original, original), "");
+}
+
+#[test]
+fn test_search_replace_equivalent_grows_context_until_unique() {
+    let original = "x\nx\nx\nx\nx\nx\nx\nx\nx\nx\nend\n";
+    let updated = "x\nx\nx\nx\nx\nx\nx\nx\nx\nx\nnew\nend\n";
+    let diff = search_replace_equivalent(original, updated);
+    assert_eq!(
+        construct_new_file_content_v2(&diff, original, true).unwrap(),
+        updated
+    );
+}
+
+#[test]
+fn test_search_replace_equivalent_escapes_marker_lines() {
+    let original = "<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> branch\n";
+    let updated = "ours\n";
+    let diff = search_replace_equivalent(original, updated);
+    assert!(diff.contains("\n\\=======\n"));
+    assert_eq!(
+        construct_new_file_content_v2(&diff, original, true).unwrap(),
+        updated
+    );
+}
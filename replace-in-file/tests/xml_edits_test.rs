@@ -0,0 +1,199 @@
+use replace_in_file::{
+    DiffError, DiffOptions, EditBlock, Limits, Precondition, construct_new_file_content_v2,
+    construct_new_file_content_xml, construct_new_file_content_xml_with_options,
+    parse_search_replace_blocks, parse_xml_edit_blocks,
+};
+
+// XML-tagged edit format: same block model and matching engine as SEARCH/REPLACE
+
+#[test]
+fn test_xml_and_search_replace_produce_same_blocks() {
+    let xml = "<edit>
+<old>
+fn main() {
+    println!(\"hi\");
+}
+</old>
+<new>
+fn main() {}
+</new>
+</edit>";
+    let diff = "------- SEARCH
+fn main() {
+    println!(\"hi\");
+}
+=======
+fn main() {}
++++++++ REPLACE";
+
+    let from_xml = parse_xml_edit_blocks(xml).unwrap();
+    let from_diff = parse_search_replace_blocks(diff, &DiffOptions::default()).unwrap();
+    assert_eq!(from_xml, from_diff);
+    assert_eq!(
+        from_xml,
+        vec![EditBlock {
+            search: "fn main() {\n    println!(\"hi\");\n}\n".to_string(),
+            replace: "fn main() {}\n".to_string(),
+            ..Default::default()
+        }]
+    );
+}
+
+#[test]
+fn test_xml_and_search_replace_apply_identically() {
+    let original = "line1\n line2 \nline3\nline4";
+    let xml = "<edit><old>line2</old><new>replaced</new></edit>";
+    let diff = "------- SEARCH\nline2\n=======\nreplaced\n+++++++ REPLACE";
+
+    let r1 = construct_new_file_content_xml(xml, original, true).unwrap();
+    let r2 = construct_new_file_content_v2(diff, original, true).unwrap();
+    assert_eq!(r1, "line1\nreplaced\nline3\nline4");
+    assert_eq!(r1, r2);
+}
+
+#[test]
+fn test_xml_entities_and_cdata() {
+    let original = "if a < b && c > d {\n    x = \"</old>\";\n}\n";
+    let xml = "<edit>
+<old>
+if a &lt; b && c &gt; d {
+<![CDATA[    x = \"</old>\";]]>
+</old>
+<new>
+if a &#60; b &amp;&amp; c &#x3E; d {
+    x = &quot;done&quot;;
+</new>
+</edit>";
+    let expected = "if a < b && c > d {\n    x = \"done\";\n}\n";
+
+    let result = construct_new_file_content_xml(xml, original, true).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_xml_multiple_edits_with_surrounding_text() {
+    let original = "a\nb\nc\nd\n";
+    let xml = "I'll update two lines.
+<edits>
+  <edit>
+    <old>
+b
+    </old>
+    <new>
+B
+    </new>
+  </edit>
+  <edit>
+    <new>D</new>
+    <old>d</old>
+  </edit>
+</edits>";
+    let expected = "a\nB\nc\nD\n";
+
+    let result = construct_new_file_content_xml(xml, original, true).unwrap();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_xml_empty_new_deletes_and_empty_old_creates() {
+    let deleted =
+        construct_new_file_content_xml("<edit><old>b</old><new/></edit>", "a\nb\nc\n", true)
+            .unwrap();
+    assert_eq!(deleted, "a\nc\n");
+
+    let created =
+        construct_new_file_content_xml("<edit><old></old><new>hello</new></edit>", "", true)
+            .unwrap();
+    assert_eq!(created, "hello\n");
+}
+
+#[test]
+fn test_xml_marker_like_content_needs_no_escaping() {
+    let original = "ours\n=======\ntheirs\n";
+    let xml = "<edit><old>
+ours
+=======
+theirs
+</old><new>
+=======
+</new></edit>";
+
+    let result = construct_new_file_content_xml(xml, original, true).unwrap();
+    assert_eq!(result, "=======\n");
+}
+
+#[test]
+fn test_xml_malformed_edits_are_errors() {
+    assert!(parse_xml_edit_blocks("<edit><old>a</old></edit>").is_err());
+    assert!(parse_xml_edit_blocks("<edit><old>a</old><old>b</old><new>c</new></edit>").is_err());
+    assert!(parse_xml_edit_blocks("<edit><old>a</old><new>b</new>").is_err());
+    assert!(parse_xml_edit_blocks("<edit>junk<old>a</old><new>b</new></edit>").is_err());
+    assert!(
+        parse_xml_edit_blocks("<editor>not an edit</editor>")
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_xml_streaming_ignores_unterminated_edit() {
+    let original = "a\nb\nc\n";
+    let xml = "<edit><old>a</old><new>A</new></edit>\n<edit><old>c</old><new>C";
+
+    let partial = construct_new_file_content_xml(xml, original, false).unwrap();
+    assert_eq!(partial, "A\n");
+    assert!(construct_new_file_content_xml(xml, original, true).is_err());
+}
+
+#[test]
+fn test_xml_unmatched_old_reports_search_block_error() {
+    let result = construct_new_file_content_xml(
+        "<edit><old>missing</old><new>x</new></edit>",
+        "a\nb\n",
+        true,
+    );
+    assert!(result.unwrap_err().to_string().contains("missing"));
+}
+
+#[test]
+fn test_options_apply_to_xml_edits() {
+    let original = "a\nb\n";
+    let xml = "<edit><old>a</old><new>x</new></edit><edit><old>b</old><new>y</new></edit>";
+    let with_limits = |limits| DiffOptions {
+        limits,
+        ..Default::default()
+    };
+
+    let one_block = with_limits(Limits {
+        max_blocks: Some(1),
+        ..Default::default()
+    });
+    assert!(matches!(
+        construct_new_file_content_xml_with_options(xml, original, true, &one_block),
+        Err(DiffError::TooManyBlocks { limit: 1 })
+    ));
+
+    let small_diff = with_limits(Limits {
+        max_diff_bytes: Some(10),
+        ..Default::default()
+    });
+    assert!(matches!(
+        construct_new_file_content_xml_with_options(xml, original, true, &small_diff),
+        Err(DiffError::DiffTooLarge { .. })
+    ));
+
+    let stale = DiffOptions {
+        expected_original: Some(Precondition::Sha256("0".repeat(64))),
+        ..Default::default()
+    };
+    assert!(matches!(
+        construct_new_file_content_xml_with_options(xml, original, true, &stale),
+        Err(DiffError::StaleOriginal { .. })
+    ));
+
+    assert_eq!(
+        construct_new_file_content_xml_with_options(xml, original, true, &DiffOptions::default())
+            .unwrap(),
+        "x\ny\n"
+    );
+}